@@ -1,14 +1,20 @@
 use clap::{Args, Parser, Subcommand};
 
 use super::InternalCommand;
+use crate::formatter::{OutputFormat, StatsSort};
+use crate::utility::MatchMode;
 
 #[derive(Parser)]
 #[command(author, version, about)]
 pub struct App {
     /// Runs the command as sudo on the remote nodes
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "no_sudo")]
     pub sudo: bool,
 
+    /// Never runs the command as sudo on the remote nodes, even if the config file enables it
+    #[arg(long)]
+    pub no_sudo: bool,
+
     /// Filters nodes on a given patterns
     #[arg(short, long, value_name = "regex")]
     pub regex: Option<String>,
@@ -17,6 +23,49 @@ pub struct App {
     #[arg(short, long, value_name = "identity-file")]
     pub identity_file: Option<String>,
 
+    /// Inventory file to read nodes from (default ~/.config/docker-manager/inventory.toml)
+    #[arg(long, value_name = "path")]
+    pub inventory: Option<String>,
+
+    /// Only use nodes in the given inventory group, can be given multiple times
+    #[arg(short, long, value_name = "group")]
+    pub group: Vec<String>,
+
+    /// Only use nodes that have the given inventory tag, can be given multiple times
+    #[arg(short, long, value_name = "tag")]
+    pub tag: Vec<String>,
+
+    /// Maximum number of nodes that are connected to in parallel (default 10)
+    #[arg(short, long, value_name = "n")]
+    pub concurrency: Option<usize>,
+
+    /// Seconds to wait for an ssh connection to a node to be established (default 1)
+    #[arg(long, value_name = "seconds")]
+    pub connect_timeout: Option<u64>,
+
+    /// Seconds a command may take on a single node before it is aborted, this does not apply to
+    /// `exec --interactive` and `logs --follow`
+    #[arg(long, value_name = "seconds")]
+    pub timeout: Option<u64>,
+
+    /// Format of the output (default table)
+    #[arg(short, long, value_name = "format")]
+    pub output: Option<OutputFormat>,
+
+    /// How container names and ids are matched against the containers on the nodes
+    #[arg(
+        short,
+        long = "match",
+        value_name = "mode",
+        default_value_t,
+        value_enum
+    )]
+    pub match_mode: MatchMode,
+
+    /// Profile from ~/.config/docker-manager/config.toml to take default flags from
+    #[arg(short, long, value_name = "profile")]
+    pub profile: Option<String>,
+
     /// This command will be ran on the remote nodes
     #[command(subcommand)]
     pub command: Command,
@@ -28,7 +77,11 @@ pub enum Command {
     /// "$(docker-manager completion)"` to your ~/.bashrc.
     Completion { shell: clap_complete::Shell },
 
-    /// Execute a command on a given container unless 2 or more containers are found on remote nodes
+    /// Create a new container on the node picked by `--node`, `--on-least-loaded` or `--on-group`
+    Create(RunArgs),
+
+    /// Execute a command on a given container unless 2 or more containers are found on remote
+    /// nodes, see `--all-matches`
     Exec {
         /// Container name or id
         container_id: String,
@@ -60,10 +113,10 @@ pub enum Command {
         #[arg(long)]
         privileged: bool,
 
-        // This one is not useful for us
-        ///// Allocate a pseudo-TTY
-        //#[arg(short, long)]
-        //tty: bool,
+        /// Allocate a pseudo-TTY
+        #[arg(short, long)]
+        tty: bool,
+
         /// Username or UID (format: <name|uid>[:<group|gid>])
         #[arg(short, long, value_name = "string")]
         user: Option<String>,
@@ -71,6 +124,21 @@ pub enum Command {
         /// Working directory inside the container
         #[arg(short, long, value_name = "string")]
         workdir: Option<String>,
+
+        /// Node the container runs on, this skips looking the container up on every node
+        #[arg(long, value_name = "host")]
+        node: Option<String>,
+
+        /// Run the command in every matching container on every node instead of exactly one
+        #[arg(long, conflicts_with_all = ["interactive", "tty"])]
+        all_matches: bool,
+    },
+
+    /// Run a shell command on the remote nodes themselves instead of in a container
+    HostExec {
+        /// Command that is passed to the shell of every node, e.g. `-- df -h /var/lib/docker`
+        #[arg(required = true)]
+        command: Vec<String>,
     },
 
     /// List all images on remote nodes
@@ -100,10 +168,39 @@ pub enum Command {
         quiet: bool,
     },
 
-    /// Gets the logs of a given container unless 2 or more containers are found on remote nodes
-    Logs {
+    /// Return low-level information on containers, found on any node, or on other docker
+    /// objects such as images
+    Inspect {
+        /// Container name or id, anything that is not a container is looked up on every node
+        #[arg(required = true)]
+        container_id: Vec<String>,
+
+        /// Format output using a custom Go template
+        #[arg(short, long, value_name = "string")]
+        format: Option<String>,
+
+        /// Display total file sizes if the type is container
+        #[arg(short, long)]
+        size: bool,
+    },
+
+    /// Kill one or more running containers
+    Kill {
+        /// Signal to send to the container (default KILL)
+        #[arg(short, long, value_name = "string")]
+        signal: Option<String>,
+
         /// Container name or id
-        container_id: String,
+        #[arg(required = true)]
+        container_id: Vec<String>,
+    },
+
+    /// Gets the logs of every matching container, interleaved when there are several
+    Logs {
+        /// Container name or id, every running container is searched when this is left out
+        /// with `--grep`
+        #[arg(required_unless_present = "grep")]
+        container_id: Option<String>,
 
         /// Show extra details provided to logs
         #[arg(long)]
@@ -128,6 +225,36 @@ pub enum Command {
         /// Show logs before a timestamp (e.g. 2013-01-02T13:23:37Z) or relative (e.g. 42m for 42 minutes)
         #[arg(long, value_name = "string")]
         until: Option<String>,
+
+        /// Node the container runs on, this skips looking the container up on every node
+        #[arg(long, value_name = "host")]
+        node: Option<String>,
+
+        /// Only show lines matching a regular expression, grouped by container
+        #[arg(long, value_name = "regex", conflicts_with = "follow")]
+        grep: Option<String>,
+
+        /// Number of lines to show before and after every line matching `--grep`
+        #[arg(
+            short = 'C',
+            long,
+            value_name = "n",
+            default_value_t = 0,
+            requires = "grep"
+        )]
+        context: usize,
+
+        /// Write the logs to one file per host and container in the given directory, together
+        /// with a manifest, instead of printing them
+        #[arg(long, value_name = "dir", conflicts_with_all = ["follow", "grep"])]
+        export: Option<String>,
+    },
+
+    /// Pause all processes within one or more containers
+    Pause {
+        /// Container name or id
+        #[arg(required = true)]
+        container_id: Vec<String>,
     },
 
     /// Lists all containers on remote nodes
@@ -189,6 +316,17 @@ pub enum Command {
         volumes: bool,
     },
 
+    /// Create and run a new container on the node picked by `--node`, `--on-least-loaded` or
+    /// `--on-group`
+    Run {
+        #[command(flatten)]
+        args: RunArgs,
+
+        /// Run container in background and print container ID
+        #[arg(short, long)]
+        detach: bool,
+    },
+
     /// Starts one or more given containers
     Start {
         /// Attach STDOUT/STDERR and forward signals
@@ -199,6 +337,28 @@ pub enum Command {
         container_id: Vec<String>,
     },
 
+    /// Display a live table of the resource usage of containers on all remote nodes
+    Stats {
+        /// Container name or id, all running containers are shown when this is left out
+        container_id: Vec<String>,
+
+        /// Show all containers (default shows just running)
+        #[arg(short, long)]
+        all: bool,
+
+        /// Print the table once instead of refreshing it until ctrl-c is pressed
+        #[arg(long)]
+        no_stream: bool,
+
+        /// Do not truncate output
+        #[arg(long)]
+        no_trunc: bool,
+
+        /// Sort containers and nodes by their usage, highest first
+        #[arg(long, value_name = "column", value_enum)]
+        sort: Option<StatsSort>,
+    },
+
     /// Stops one or more given containers
     Stop {
         /// Container name or id
@@ -207,6 +367,98 @@ pub enum Command {
 
     /// Manage Docker
     System(System),
+
+    /// Display the running processes of one or more containers
+    Top {
+        /// Container name or id
+        #[arg(required = true)]
+        container_id: Vec<String>,
+
+        /// Options passed to ps inside the container, e.g. `-- aux`
+        #[arg(last = true, value_name = "ps options")]
+        options: Vec<String>,
+    },
+
+    /// Unpause all processes within one or more containers
+    Unpause {
+        /// Container name or id
+        #[arg(required = true)]
+        container_id: Vec<String>,
+    },
+}
+
+/// The flags `run` and `create` share.
+#[derive(Args, Clone, Debug)]
+pub struct RunArgs {
+    /// Image the container is created from
+    pub image: String,
+
+    /// Command that is ran in the container instead of the default of the image
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub command: Vec<String>,
+
+    /// Node the container is created on
+    #[arg(
+        long,
+        value_name = "host",
+        required_unless_present_any = ["on_least_loaded", "on_group"],
+        conflicts_with_all = ["on_least_loaded", "on_group"]
+    )]
+    pub node: Option<String>,
+
+    /// Create the container on the selected node that runs the fewest containers
+    #[arg(long)]
+    pub on_least_loaded: bool,
+
+    /// Create the container on the least loaded nodes of the given inventory group
+    #[arg(long, value_name = "group")]
+    pub on_group: Option<String>,
+
+    /// Number of least loaded nodes the container is created on, one container per node
+    /// (default 1)
+    #[arg(
+        long,
+        value_name = "n",
+        conflicts_with = "node",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub replicas: Option<u32>,
+
+    /// Publish a container's port(s) to the host
+    #[arg(short, long, value_name = "list")]
+    pub publish: Vec<String>,
+
+    /// Bind mount a volume
+    #[arg(short, long, value_name = "list")]
+    pub volume: Vec<String>,
+
+    /// Set environment variables
+    #[arg(short, long, value_name = "list")]
+    pub env: Vec<String>,
+
+    /// Read in a file of environment variables
+    #[arg(long, value_name = "list")]
+    pub env_file: Vec<String>,
+
+    /// Assign a name to the container
+    #[arg(long, value_name = "string")]
+    pub name: Option<String>,
+
+    /// Restart policy to apply when a container exits (default "no")
+    #[arg(long, value_name = "string")]
+    pub restart: Option<String>,
+
+    /// Set meta data on a container
+    #[arg(short, long, value_name = "list")]
+    pub label: Vec<String>,
+
+    /// Connect a container to a network
+    #[arg(long, value_name = "network")]
+    pub network: Option<String>,
+
+    /// Automatically remove the container when it exits
+    #[arg(long)]
+    pub rm: bool,
 }
 
 #[derive(Args, Clone, Debug)]
@@ -283,8 +535,9 @@ impl std::fmt::Display for Command {
 }
 
 impl Command {
-    pub fn internal_reference_command(&self) -> InternalCommand {
+    pub fn internal_reference_command(&self) -> InternalCommand<'_> {
         match self {
+            Self::Create(args) => InternalCommand::Create(args),
             Self::Exec {
                 container_id,
                 command,
@@ -294,8 +547,11 @@ impl Command {
                 env_file,
                 interactive,
                 privileged,
+                tty,
                 user,
                 workdir,
+                node,
+                all_matches,
             } => {
                 let mut commands: Vec<&str> = vec![];
                 for com in command {
@@ -335,10 +591,16 @@ impl Command {
                     env_file: _env_file,
                     interactive: *interactive,
                     privileged: *privileged,
+                    tty: *tty,
                     user: user.as_deref(),
                     workdir: workdir.as_deref(),
+                    node: node.as_deref(),
+                    all_matches: *all_matches,
                 }
             }
+            Self::HostExec { command } => InternalCommand::HostExec {
+                command: command.iter().map(String::as_str).collect(),
+            },
             Self::Images {
                 all,
                 digest,
@@ -366,6 +628,22 @@ impl Command {
                     quiet: *quiet,
                 }
             }
+            Self::Inspect {
+                container_id,
+                format,
+                size,
+            } => InternalCommand::Inspect {
+                container_id: container_id.iter().map(String::as_str).collect(),
+                format: format.as_deref(),
+                size: *size,
+            },
+            Self::Kill {
+                signal,
+                container_id,
+            } => InternalCommand::Kill {
+                container_id: container_id.iter().map(String::as_str).collect(),
+                signal: signal.as_deref(),
+            },
             Self::Logs {
                 container_id,
                 details,
@@ -374,6 +652,10 @@ impl Command {
                 tail,
                 timestamps,
                 until,
+                node,
+                grep: _,
+                context: _,
+                export,
             } => {
                 let _since: Option<&str> = match since {
                     Some(s) => Some(s),
@@ -391,15 +673,21 @@ impl Command {
                 };
 
                 InternalCommand::Logs {
-                    container_id,
+                    container_id: container_id.as_deref(),
                     details: *details,
                     follow: *follow,
                     since: _since,
                     tail: _tail,
-                    timestamps: *timestamps,
+                    // exported logs of several containers can only be lined up with timestamps
+                    timestamps: *timestamps || export.is_some(),
                     until: _until,
+                    node: node.as_deref(),
+                    sink: None,
                 }
             }
+            Self::Pause { container_id } => InternalCommand::Pause {
+                container_id: container_id.iter().map(String::as_str).collect(),
+            },
             Self::Ps {
                 all,
                 filter,
@@ -464,6 +752,10 @@ impl Command {
                     volumes: *volumes,
                 }
             }
+            Self::Run { args, detach } => InternalCommand::Run {
+                args,
+                detach: *detach,
+            },
             Self::Start {
                 attach,
                 container_id,
@@ -479,6 +771,17 @@ impl Command {
                     container_id: _container_id,
                 }
             }
+            Self::Stats {
+                container_id,
+                all,
+                no_stream: _,
+                no_trunc,
+                sort: _,
+            } => InternalCommand::Stats {
+                container_id: container_id.iter().map(String::as_str).collect(),
+                all: *all,
+                no_trunc: *no_trunc,
+            },
             Self::Stop { container_id } => {
                 let mut _container_id: Vec<&str> = vec![];
 
@@ -491,7 +794,109 @@ impl Command {
                 }
             }
             Self::System(s) => InternalCommand::System(s.clone()),
+            Self::Top {
+                container_id,
+                options,
+            } => InternalCommand::Top {
+                container_id: container_id.iter().map(String::as_str).collect(),
+                options: options.iter().map(String::as_str).collect(),
+            },
+            Self::Unpause { container_id } => InternalCommand::Unpause {
+                container_id: container_id.iter().map(String::as_str).collect(),
+            },
             _ => unreachable!(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::{App, Command};
+
+    #[test]
+    fn test_run_command_keeps_hyphen_arguments() {
+        let app = App::try_parse_from([
+            "docker-manager",
+            "run",
+            "--node",
+            "x",
+            "alpine",
+            "ls",
+            "-la",
+        ])
+        .unwrap();
+
+        let Command::Run { args, .. } = app.command else {
+            panic!("expected the run command");
+        };
+
+        assert_eq!(args.image, "alpine");
+        assert_eq!(args.command, vec!["ls", "-la"]);
+        assert!(args.label.is_empty());
+    }
+
+    #[test]
+    fn test_run_command_after_image_is_not_parsed() {
+        let app = App::try_parse_from([
+            "docker-manager",
+            "run",
+            "--node",
+            "x",
+            "alpine",
+            "sh",
+            "-c",
+            "echo hi",
+        ])
+        .unwrap();
+
+        let Command::Run { args, .. } = app.command else {
+            panic!("expected the run command");
+        };
+
+        assert_eq!(args.command, vec!["sh", "-c", "echo hi"]);
+    }
+
+    #[test]
+    fn test_run_placement() {
+        let app = App::try_parse_from([
+            "docker-manager",
+            "run",
+            "--on-group",
+            "prod",
+            "--replicas",
+            "3",
+            "nginx",
+        ])
+        .unwrap();
+
+        let Command::Run { args, .. } = app.command else {
+            panic!("expected the run command");
+        };
+
+        assert_eq!(args.on_group.as_deref(), Some("prod"));
+        assert_eq!(args.replicas, Some(3));
+        assert!(
+            App::try_parse_from(["docker-manager", "run", "--on-least-loaded", "nginx"]).is_ok()
+        );
+        assert!(App::try_parse_from([
+            "docker-manager",
+            "run",
+            "--on-least-loaded",
+            "--replicas",
+            "0",
+            "nginx"
+        ])
+        .is_err());
+        assert!(App::try_parse_from(["docker-manager", "run", "nginx"]).is_err());
+    }
+
+    #[test]
+    fn test_container_id_required() {
+        for command in ["kill", "pause", "unpause"] {
+            assert!(App::try_parse_from(["docker-manager", command]).is_err());
+            assert!(App::try_parse_from(["docker-manager", command, "web"]).is_ok());
+        }
+    }
+}
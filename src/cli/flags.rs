@@ -1,3 +1,6 @@
+use super::RunArgs;
+use crate::formatter::JSON_FORMAT;
+
 pub struct LogsFlags<'a> {
     pub details: bool,
     pub follow: bool,
@@ -80,7 +83,7 @@ pub struct ExecFlags<'a> {
     pub env_file: Vec<&'a str>,
     pub interactive: bool,
     pub privileged: bool,
-    //pub tty: bool,
+    pub tty: bool,
     pub user: &'a str,
     pub workdir: &'a str,
 }
@@ -94,7 +97,7 @@ impl<'a> ExecFlags<'a> {
         env_file: Option<Vec<&'a str>>,
         interactive: bool,
         privileged: bool,
-        //tty: bool,
+        tty: bool,
         user: &'a Option<&'a str>,
         workdir: &'a Option<&'a str>,
     ) -> Self {
@@ -103,15 +106,9 @@ impl<'a> ExecFlags<'a> {
             None => "",
         };
 
-        let env = match env {
-            Some(e) => e,
-            None => vec![],
-        };
+        let env = env.unwrap_or_default();
 
-        let env_file = match env_file {
-            Some(e) => e,
-            None => vec![],
-        };
+        let env_file = env_file.unwrap_or_default();
 
         let user = match &user {
             Some(u) => u,
@@ -130,7 +127,7 @@ impl<'a> ExecFlags<'a> {
             env_file,
             interactive,
             privileged,
-            //tty,
+            tty,
             user,
             workdir,
         }
@@ -150,6 +147,10 @@ impl<'a> ExecFlags<'a> {
             v.push("--privileged");
         }
 
+        if self.tty {
+            v.push("--tty");
+        }
+
         if !self.detach_keys.is_empty() {
             v.push("--detach-keys");
             v.push(self.detach_keys);
@@ -178,8 +179,6 @@ impl<'a> ExecFlags<'a> {
             v.push("--workdir");
             v.push(self.workdir);
         }
-        // This one is not actually useful since this program is not a tty
-        // tty: bool,
 
         v
     }
@@ -226,11 +225,11 @@ impl<'a> ImagesFlags<'a> {
     pub fn flags(&self) -> Vec<&str> {
         let mut v: Vec<&str> = vec![];
         if self.all {
-            v.push("-");
+            v.push("-a");
         }
 
         if self.digest {
-            v.push("--digest");
+            v.push("--digests");
         }
 
         if !self.filter.is_empty() {
@@ -241,6 +240,10 @@ impl<'a> ImagesFlags<'a> {
         if !self.format.is_empty() {
             v.push("--format");
             v.push(self.format);
+        } else if !self.quiet {
+            // parsed into `ImageSummary` instead of splitting the table docker prints
+            v.push("--format");
+            v.push(JSON_FORMAT);
         }
 
         if self.no_trunc {
@@ -313,21 +316,21 @@ impl<'a> PsFlags<'a> {
         if !self.format.is_empty() {
             v.push("--format");
             v.push(self.format);
+        } else if !self.quiet {
+            // parsed into `ContainerSummary` instead of splitting the table docker prints
+            v.push("--format");
+            v.push(JSON_FORMAT);
         };
 
         if self.last {
             v.push("--last")
         }
 
-        if self.last {
-            v.push("--last")
-        }
-
         if self.latests {
-            v.push("--latests")
+            v.push("--latest")
         }
         if self.no_trunc {
-            v.push("--no_trunc")
+            v.push("--no-trunc")
         }
         if self.quiet {
             v.push("--quiet")
@@ -364,3 +367,185 @@ impl RmFlags {
         v
     }
 }
+
+pub struct InspectFlags<'a> {
+    pub format: Option<&'a str>,
+    pub size: bool,
+}
+
+impl<'a> InspectFlags<'a> {
+    pub fn new(format: Option<&'a str>, size: bool) -> Self {
+        Self { format, size }
+    }
+
+    pub fn flags(&self) -> Vec<&'a str> {
+        let mut v: Vec<&str> = vec![];
+
+        if let Some(format) = self.format {
+            v.push("--format");
+            v.push(format);
+        }
+
+        if self.size {
+            v.push("-s")
+        }
+
+        v
+    }
+}
+
+pub struct StatsFlags {
+    pub all: bool,
+    pub no_trunc: bool,
+}
+
+impl StatsFlags {
+    pub fn new(all: bool, no_trunc: bool) -> Self {
+        Self { all, no_trunc }
+    }
+
+    pub fn flags(&self) -> Vec<&'static str> {
+        // refreshing the table is done by running stats again, the output is parsed into
+        // `StatsSummary`
+        let mut v: Vec<&str> = vec!["--no-stream", "--format", JSON_FORMAT];
+
+        if self.all {
+            v.push("-a")
+        }
+
+        if self.no_trunc {
+            v.push("--no-trunc")
+        }
+
+        v
+    }
+}
+
+pub struct RunFlags<'a> {
+    pub publish: &'a [String],
+    pub volume: &'a [String],
+    pub env: &'a [String],
+    pub env_file: &'a [String],
+    pub name: Option<&'a str>,
+    pub restart: Option<&'a str>,
+    pub label: &'a [String],
+    pub network: Option<&'a str>,
+    pub detach: bool,
+    pub rm: bool,
+}
+
+impl<'a> RunFlags<'a> {
+    /// `detach` is only passed for `run`, `create` never attaches to the container.
+    pub fn new(args: &'a RunArgs, detach: bool) -> Self {
+        Self {
+            publish: &args.publish,
+            volume: &args.volume,
+            env: &args.env,
+            env_file: &args.env_file,
+            name: args.name.as_deref(),
+            restart: args.restart.as_deref(),
+            label: &args.label,
+            network: args.network.as_deref(),
+            detach,
+            rm: args.rm,
+        }
+    }
+
+    pub fn flags(&self) -> Vec<&'a str> {
+        let mut v: Vec<&str> = vec![];
+
+        for (flag, values) in [
+            ("--publish", self.publish),
+            ("--volume", self.volume),
+            ("--env", self.env),
+            ("--env-file", self.env_file),
+            ("--label", self.label),
+        ] {
+            for value in values {
+                v.push(flag);
+                v.push(value);
+            }
+        }
+
+        for (flag, value) in [
+            ("--name", self.name),
+            ("--restart", self.restart),
+            ("--network", self.network),
+        ] {
+            if let Some(value) = value {
+                v.push(flag);
+                v.push(value);
+            }
+        }
+
+        if self.detach {
+            v.push("--detach")
+        }
+
+        if self.rm {
+            v.push("--rm")
+        }
+
+        v
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ImagesFlags, PsFlags, RunFlags};
+
+    #[test]
+    fn test_images_flags() {
+        let flags = ImagesFlags::new(true, true, &None, &None, true, true);
+
+        assert_eq!(
+            vec!["-a", "--digests", "--no-trunc", "--quiet"],
+            flags.flags()
+        );
+    }
+
+    #[test]
+    fn test_ps_flags() {
+        let flags = PsFlags::new(false, &None, &None, true, true, true, true, false);
+
+        assert_eq!(
+            vec!["--last", "--latest", "--no-trunc", "--quiet"],
+            flags.flags()
+        );
+    }
+
+    #[test]
+    fn test_run_flags() {
+        let publish = vec!["80:80".to_string(), "443:443".to_string()];
+        let env = vec!["MODE=prod".to_string()];
+        let flags = RunFlags {
+            publish: &publish,
+            volume: &[],
+            env: &env,
+            env_file: &[],
+            name: Some("web"),
+            restart: Some("always"),
+            label: &[],
+            network: None,
+            detach: true,
+            rm: false,
+        };
+
+        assert_eq!(
+            vec![
+                "--publish",
+                "80:80",
+                "--publish",
+                "443:443",
+                "--env",
+                "MODE=prod",
+                "--name",
+                "web",
+                "--restart",
+                "always",
+                "--detach",
+            ],
+            flags.flags()
+        );
+    }
+}
@@ -1,7 +1,12 @@
-use super::System;
+use super::{RunArgs, System};
+use crate::formatter::LogSink;
 
 pub enum InternalCommand<'a> {
-    /// Execute a command on a given container unless 2 or more containers are found on remote nodes
+    /// Create a new container, the node is picked from the placement flags in `RunArgs`
+    Create(&'a RunArgs),
+
+    /// Execute a command on a given container unless 2 or more containers are found on remote
+    /// nodes, see `all_matches`
     Exec {
         /// Container name or id
         container_id: &'a str,
@@ -27,11 +32,26 @@ pub enum InternalCommand<'a> {
         /// Give extended privileges to the command
         privileged: bool,
 
+        /// Allocate a pseudo-TTY
+        tty: bool,
+
         /// Username or UID (format: <name|uid>[:<group|gid>])
         user: Option<&'a str>,
 
         /// Working directory inside the container
         workdir: Option<&'a str>,
+
+        /// Node the container runs on
+        node: Option<&'a str>,
+
+        /// Run the command in every matching container
+        all_matches: bool,
+    },
+
+    /// Run a shell command on the remote nodes themselves
+    HostExec {
+        /// Command that is passed to the shell of every node
+        command: Vec<&'a str>,
     },
 
     /// List all images on remote nodes
@@ -55,10 +75,31 @@ pub enum InternalCommand<'a> {
         quiet: bool,
     },
 
-    /// Gets the logs of a given container unless 2 or more containers are found on remote nodes
-    Logs {
+    /// Return low-level information on containers or other docker objects
+    Inspect {
+        /// Container name or id, or the name of any other docker object
+        container_id: Vec<&'a str>,
+
+        /// Format output using a custom Go template
+        format: Option<&'a str>,
+
+        /// Display total file sizes if the type is container
+        size: bool,
+    },
+
+    /// Kill one or more running containers
+    Kill {
         /// Container name or id
-        container_id: &'a str,
+        container_id: Vec<&'a str>,
+
+        /// Signal to send to the container
+        signal: Option<&'a str>,
+    },
+
+    /// Gets the logs of every matching container, interleaved when there are several
+    Logs {
+        /// Container name or id, this is only left out to search every running container
+        container_id: Option<&'a str>,
 
         /// Show extra details provided to logs
         details: bool,
@@ -77,6 +118,18 @@ pub enum InternalCommand<'a> {
 
         /// Show logs before a timestamp (e.g. 2013-01-02T13:23:37Z) or relative (e.g. 42m for 42 minutes)
         until: Option<&'a str>,
+
+        /// Node the container runs on
+        node: Option<&'a str>,
+
+        /// Receives the lines of followed logs, this is set when the container is known
+        sink: Option<&'a LogSink>,
+    },
+
+    /// Pause all processes within one or more containers
+    Pause {
+        /// Container name or id
+        container_id: Vec<&'a str>,
     },
 
     /// Lists all containers on remote nodes
@@ -127,6 +180,14 @@ pub enum InternalCommand<'a> {
         volumes: bool,
     },
 
+    /// Create and run a new container, the node is picked from the placement flags in `RunArgs`
+    Run {
+        args: &'a RunArgs,
+
+        /// Run container in background and print container ID
+        detach: bool,
+    },
+
     /// Starts a given container unless 2 or more containers are found on remote nodes
     Start {
         /// Attach STDOUT/STDERR and forward signals
@@ -136,6 +197,18 @@ pub enum InternalCommand<'a> {
         container_id: Vec<&'a str>,
     },
 
+    /// Display the resource usage of containers once
+    Stats {
+        /// Container name or id, all containers are shown when this is empty
+        container_id: Vec<&'a str>,
+
+        /// Show all containers (default shows just running)
+        all: bool,
+
+        /// Do not truncate output
+        no_trunc: bool,
+    },
+
     /// Stops a given container unless 2 or more containers are found on remote nodes
     Stop {
         /// Container name or id
@@ -144,4 +217,34 @@ pub enum InternalCommand<'a> {
 
     /// Manage Docker
     System(System),
+
+    /// Display the running processes of containers, `docker top` takes a single container so
+    /// this is ran once per container
+    Top {
+        /// Container name or id
+        container_id: Vec<&'a str>,
+
+        /// Options passed to ps inside the container
+        options: Vec<&'a str>,
+    },
+
+    /// Unpause all processes within one or more containers
+    Unpause {
+        /// Container name or id
+        container_id: Vec<&'a str>,
+    },
+}
+
+impl<'a> InternalCommand<'a> {
+    /// Commands that keep running until they are interrupted.
+    pub fn is_streaming(&self) -> bool {
+        matches!(
+            self,
+            Self::Exec {
+                interactive: true,
+                ..
+            } | Self::Exec { tty: true, .. }
+                | Self::Logs { follow: true, .. }
+        )
+    }
 }
@@ -2,5 +2,5 @@ mod app;
 pub mod flags;
 mod internal_command;
 
-pub use app::{App, Command, System, SystemCommand};
+pub use app::{App, Command, RunArgs, System, SystemCommand};
 pub use internal_command::InternalCommand;
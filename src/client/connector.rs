@@ -1,48 +1,154 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
-use crate::cli::flags::{ExecFlags, ImagesFlags, LogsFlags, PsFlags, RmFlags};
+use crate::cli::flags::{
+    ExecFlags, ImagesFlags, InspectFlags, LogsFlags, PsFlags, RmFlags, RunFlags, StatsFlags,
+};
 use crate::cli::InternalCommand;
-use crate::utility::command;
+use crate::utility::command::{self, Docker, RemoteProcess, Sudo};
+use crate::utility::{config_dir, expand_tilde, home_dir, MatchMode, NodeOutput};
 
+use super::inventory::{Inventory, InventoryHost};
+use super::ssh_config::{self, SshHost};
+
+use openssh::Session;
 use regex::Regex;
+use tokio::sync::{Mutex, OnceCell};
 
-#[derive(Debug)]
-pub struct Client {
-    nodes: Vec<Node>,
+/// Narrows down the nodes a `Client` is created with.
+#[derive(Debug, Default)]
+pub struct NodeSelector<'a> {
+    /// Path to an inventory file, the default location is used when this is not set
+    pub inventory: Option<&'a str>,
+    pub regex: Option<&'a str>,
+    /// Only select nodes in at least one of these groups
+    pub groups: &'a [String],
+    /// Only select nodes that have all of these tags
+    pub tags: &'a [String],
 }
 
-impl Client {
-    pub fn from_config<A: AsRef<Path>>(config_path: A, regex: Option<&str>) -> Self {
-        let file_contents = std::fs::read_to_string(config_path).unwrap_or_else(|_| "".into());
-
-        let _re = match regex {
+impl<'a> NodeSelector<'a> {
+    fn compile_regex(&self) -> Regex {
+        let _re = match self.regex {
             Some(pattern) => Regex::new(pattern),
             None => Regex::new(".*"),
         };
 
-        let re = match _re {
+        match _re {
             Ok(regex) => regex,
             Err(e) => {
                 eprintln!(
                     "Some error has occured while compiling your regex patterns {}\n{}",
-                    regex.unwrap(),
+                    self.regex.unwrap(),
                     e
                 );
                 std::process::exit(1)
             }
+        }
+    }
+
+    fn is_match(&self, re: &Regex, node: &Node) -> bool {
+        re.is_match(&node.address)
+            && (self.groups.is_empty() || self.groups.iter().any(|g| node.groups.contains(g)))
+            && self.tags.iter().all(|t| node.tags.contains(t))
+    }
+}
+
+/// Settings that apply to every node a command is ran on.
+#[derive(Clone, Copy, Debug)]
+pub struct RunOptions<'a> {
+    /// `Some(false)` is set by `--no-sudo`, without either flag sudo is only used when needed
+    pub sudo: Option<bool>,
+    pub identity_file: Option<&'a str>,
+    /// Maximum number of nodes that are connected to in parallel
+    pub concurrency: usize,
+    pub connect_timeout: Duration,
+    /// Deadline for running a command on a single node, including connecting to it
+    pub timeout: Option<Duration>,
+    pub match_mode: MatchMode,
+    /// Ask the user to pick a container when a lookup is ambiguous or finds nothing
+    pub pick: bool,
+}
+
+#[derive(Debug)]
+pub struct Client {
+    nodes: Vec<Node>,
+    sessions: SessionPool,
+}
+
+impl Client {
+    /// Creates a client from the inventory file if there is one, otherwise all hosts in
+    /// `~/.ssh/config` are used.
+    pub fn new(selector: &NodeSelector) -> Self {
+        let ssh_config_path = home_dir().join(".ssh").join("config");
+        let inventory_path: PathBuf = match selector.inventory {
+            Some(path) => expand_tilde(path),
+            None => config_dir().join("inventory.toml"),
         };
 
-        let mut nodes: Vec<Node> = vec![];
-        for line in file_contents.split('\n') {
-            if !line.contains('#') && line.starts_with("Host") {
-                let s: String = line.replace("  ", "").split(' ').nth(1).unwrap().into();
-                if re.is_match(&s) {
-                    nodes.push(Node::new(s));
+        if selector.inventory.is_some() || inventory_path.exists() {
+            match Self::from_inventory(&inventory_path, &ssh_config_path, selector) {
+                Ok(client) => client,
+                Err(e) => {
+                    eprintln!(
+                        "Could not read the inventory file {}\n{}",
+                        inventory_path.display(),
+                        e
+                    );
+                    std::process::exit(1)
                 }
             }
+        } else if !selector.groups.is_empty() || !selector.tags.is_empty() {
+            eprintln!(
+                "Selecting nodes by group or tag requires an inventory file at {}",
+                inventory_path.display()
+            );
+            std::process::exit(1)
+        } else {
+            Self::from_config(ssh_config_path, selector.regex)
         }
+    }
+
+    pub fn from_config<A: AsRef<Path>>(config_path: A, regex: Option<&str>) -> Self {
+        let selector = NodeSelector {
+            regex,
+            ..Default::default()
+        };
+        let re = selector.compile_regex();
 
-        Self { nodes }
+        let nodes: Vec<Node> = ssh_config::parse(config_path)
+            .into_iter()
+            .map(Node::from)
+            .filter(|node| selector.is_match(&re, node))
+            .collect();
+
+        Self {
+            nodes,
+            sessions: SessionPool::default(),
+        }
+    }
+
+    pub fn from_inventory<A: AsRef<Path>, B: AsRef<Path>>(
+        inventory_path: A,
+        config_path: B,
+        selector: &NodeSelector,
+    ) -> Result<Self, super::InventoryError> {
+        let inventory = Inventory::from_file(inventory_path)?;
+        let re = selector.compile_regex();
+
+        let nodes: Vec<Node> = inventory
+            .hosts(&ssh_config::parse(config_path))
+            .into_iter()
+            .map(Node::from)
+            .filter(|node| selector.is_match(&re, node))
+            .collect();
+
+        Ok(Self {
+            nodes,
+            sessions: SessionPool::default(),
+        })
     }
 
     pub fn nodes_info(&self) -> Vec<(&String, &Node)> {
@@ -51,37 +157,230 @@ impl Client {
             .map(|node| (&node.address, node))
             .collect()
     }
+
+    /// Looks up a node by its address.
+    pub fn node(&self, address: &str) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.address == address)
+    }
+
+    pub fn sessions(&self) -> &SessionPool {
+        &self.sessions
+    }
+}
+
+/// Keeps one ssh session per node for the duration of a single invocation so that looking up
+/// containers and running the actual command do not each open their own connection.
+#[derive(Debug, Default)]
+pub struct SessionPool {
+    sessions: Mutex<HashMap<String, Arc<OnceCell<Arc<Connection>>>>>,
+}
+
+impl SessionPool {
+    /// Returns the connection to `node`, connecting to it first if there is none yet. Concurrent
+    /// calls for the same node share a single connection attempt.
+    pub async fn get(
+        &self,
+        node: &Node,
+        options: &RunOptions<'_>,
+    ) -> Result<Arc<Connection>, NodeError> {
+        let cell = self
+            .sessions
+            .lock()
+            .await
+            .entry(node.address.clone())
+            .or_default()
+            .clone();
+
+        cell.get_or_try_init(|| async {
+            node.connect(options).await.map(|session| {
+                Arc::new(Connection {
+                    session,
+                    sudo: OnceCell::new(),
+                })
+            })
+        })
+        .await
+        .cloned()
+    }
+}
+
+/// The ssh session to a node and what was found out about the node over it.
+#[derive(Debug)]
+pub struct Connection {
+    session: Session,
+    /// Whether docker needs sudo, this is only probed for once and only when the node has no
+    /// sudo setting
+    sudo: OnceCell<Sudo>,
 }
 
 #[derive(Debug)]
 pub struct Node {
+    /// Name of the node as it is known to the user, this is the `Host` alias from the ssh config
     address: String,
+    /// `HostName` the inventory overrides the alias with, ssh resolves the alias itself otherwise
+    hostname: Option<String>,
+    /// The connection settings below are only set from the inventory, ssh applies the ones from
+    /// its own config to the alias
+    user: Option<String>,
+    port: Option<u16>,
+    /// `IdentityFile` from the ssh config, this is only used if no other identity file is given
+    identity_file: Option<String>,
+    proxy_jump: Option<String>,
+    groups: Vec<String>,
+    tags: Vec<String>,
+    overrides: NodeOverrides,
+}
+
+/// Per-node settings that take precedence over the global flags.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NodeOverrides {
+    pub sudo: Option<bool>,
+    pub identity_file: Option<String>,
+    /// Path to the docker binary on this node
+    pub docker: Option<String>,
 }
 
 impl Node {
     pub fn new(address: String) -> Self {
-        Self { address }
+        Self {
+            address,
+            hostname: None,
+            user: None,
+            port: None,
+            identity_file: None,
+            proxy_jump: None,
+            groups: vec![],
+            tags: vec![],
+            overrides: NodeOverrides::default(),
+        }
     }
 
-    pub async fn run_command(
-        &self,
-        command: InternalCommand<'_>,
-        sudo: bool,
-        identity_file: Option<&str>,
-    ) -> Result<String, NodeError> {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// The inventory groups this node is in.
+    pub fn groups(&self) -> &[String] {
+        &self.groups
+    }
+
+    /// The host ssh should connect to. This is the alias, so ssh applies every option of it from
+    /// the ssh config, unless the inventory overrides the `HostName`.
+    pub fn destination(&self) -> &str {
+        self.hostname.as_deref().unwrap_or(&self.address)
+    }
+
+    /// Opens a new ssh session to this node. The identity file from the inventory takes
+    /// precedence over the global one, which in turn takes precedence over the ssh config.
+    pub async fn connect(&self, options: &RunOptions<'_>) -> Result<Session, NodeError> {
         let mut builder = openssh::SessionBuilder::default();
-        builder.connect_timeout(std::time::Duration::new(1, 0));
+        builder.connect_timeout(options.connect_timeout);
 
-        if let Some(id_file) = identity_file {
+        if let Some(user) = &self.user {
+            builder.user(user.clone());
+        }
+
+        if let Some(port) = self.port {
+            builder.port(port);
+        }
+
+        if let Some(proxy_jump) = &self.proxy_jump {
+            builder.jump_hosts(proxy_jump.split(','));
+        }
+
+        if let Some(id_file) = self
+            .overrides
+            .identity_file
+            .as_deref()
+            .or(options.identity_file)
+            .or(self.identity_file.as_deref())
+        {
             builder.keyfile(id_file);
         };
 
-        let session = match builder.connect_mux(&self.address).await {
-            Ok(session) => session,
-            Err(e) => return Err(NodeError::SessionError(self.address.clone(), e)),
-        };
+        match builder.connect_mux(self.destination()).await {
+            Ok(session) => Ok(session),
+            Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
+        }
+    }
+
+    /// Runs a command over the session this node has in `sessions`. If a timeout is set the
+    /// command is aborted once it runs out and its remote process is killed, except for commands
+    /// that keep streaming output.
+    pub async fn run_command(
+        &self,
+        sessions: &SessionPool,
+        command: InternalCommand<'_>,
+        options: &RunOptions<'_>,
+    ) -> Result<NodeOutput, NodeError> {
+        match options.timeout {
+            Some(timeout) if !command.is_streaming() => {
+                let process: OnceLock<RemoteProcess> = OnceLock::new();
+                let running = self.execute(sessions, command, options, Some(&process));
+
+                match tokio::time::timeout(timeout, running).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        // dropping the command locally leaves docker running on the node
+                        if let Some(process) = process.get() {
+                            let kill = async {
+                                if let Ok(connection) = sessions.get(self, options).await {
+                                    let _ = process.kill(&connection.session).await;
+                                }
+                            };
+                            let _ = tokio::time::timeout(options.connect_timeout, kill).await;
+                        }
+
+                        Err(NodeError::Timeout(self.address.clone(), timeout))
+                    }
+                }
+            }
+            _ => self.execute(sessions, command, options, None).await,
+        }
+    }
+
+    /// The sudo setting from the inventory takes precedence over the global one. If sudo is not
+    /// configured at all docker is ran with sudo only when `docker version` can not access the
+    /// docker socket.
+    async fn execute(
+        &self,
+        sessions: &SessionPool,
+        command: InternalCommand<'_>,
+        options: &RunOptions<'_>,
+        process: Option<&OnceLock<RemoteProcess>>,
+    ) -> Result<NodeOutput, NodeError> {
+        let connection = sessions.get(self, options).await?;
+        let session: &Session = &connection.session;
+
+        let docker = Docker::new(
+            self.overrides.docker.as_deref().unwrap_or("docker"),
+            match self.overrides.sudo.or(options.sudo) {
+                Some(true) => Sudo::Always,
+                Some(false) => Sudo::Never,
+                None => Sudo::Auto,
+            },
+            &connection.sudo,
+            process,
+        );
 
         match command {
+            InternalCommand::Create(args) => {
+                let flags = RunFlags::new(args, false);
+                match command::run_run(
+                    &self.address,
+                    session,
+                    docker,
+                    true,
+                    &args.image,
+                    &args.command,
+                    flags,
+                )
+                .await
+                {
+                    Ok(result) => Ok(result),
+                    Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
+                }
+            }
             InternalCommand::Exec {
                 container_id,
                 command,
@@ -91,8 +390,11 @@ impl Node {
                 env_file,
                 interactive,
                 privileged,
+                tty,
                 user,
                 workdir,
+                node: _,
+                all_matches: _,
             } => {
                 let flags = ExecFlags::new(
                     detach,
@@ -101,16 +403,33 @@ impl Node {
                     env_file,
                     interactive,
                     privileged,
+                    tty,
                     &user,
                     &workdir,
                 );
-                match command::run_exec(&self.address, session, container_id, sudo, command, flags)
-                    .await
+                match command::run_exec(
+                    &self.address,
+                    session,
+                    container_id,
+                    docker,
+                    command,
+                    flags,
+                )
+                .await
                 {
                     Ok(result) => Ok(result),
                     Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
                 }
             }
+            InternalCommand::HostExec { command } => {
+                // there is no docker socket to check, so sudo is only used when asked for
+                let sudo = docker.sudo == Sudo::Always;
+
+                match command::run_host_exec(&self.address, session, sudo, command, process).await {
+                    Ok(result) => Ok(result),
+                    Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
+                }
+            }
             InternalCommand::Images {
                 all,
                 digest,
@@ -121,7 +440,30 @@ impl Node {
             } => {
                 let flags = ImagesFlags::new(all, digest, &filter, &format, no_trunc, quiet);
 
-                match command::run_images(&self.address, session, sudo, flags).await {
+                match command::run_images(&self.address, session, docker, flags).await {
+                    Ok(result) => Ok(result),
+                    Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
+                }
+            }
+            InternalCommand::Inspect {
+                container_id,
+                format,
+                size,
+            } => {
+                let flags = InspectFlags::new(format, size);
+                match command::run_inspect(&self.address, session, docker, &container_id, flags)
+                    .await
+                {
+                    Ok(result) => Ok(result),
+                    Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
+                }
+            }
+            InternalCommand::Kill {
+                container_id,
+                signal,
+            } => {
+                match command::run_kill(&self.address, session, docker, &container_id, signal).await
+                {
                     Ok(result) => Ok(result),
                     Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
                 }
@@ -134,11 +476,23 @@ impl Node {
                 tail,
                 timestamps,
                 until,
+                node: _,
+                sink,
             } => {
                 let flags = LogsFlags::new(details, follow, &since, &tail, timestamps, &until);
 
-                match command::run_logs(&self.address, session, container_id, sudo, flags).await {
-                    //, follow).await {
+                // the container is always looked up before the command reaches a node
+                let container_id = container_id.unwrap_or_default();
+
+                match command::run_logs(&self.address, session, container_id, docker, flags, sink)
+                    .await
+                {
+                    Ok(result) => Ok(result),
+                    Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
+                }
+            }
+            InternalCommand::Pause { container_id } => {
+                match command::run_pause(&self.address, session, docker, &container_id).await {
                     Ok(result) => Ok(result),
                     Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
                 }
@@ -155,13 +509,14 @@ impl Node {
             } => {
                 let flags =
                     PsFlags::new(all, &filter, &format, last, latests, no_trunc, quiet, size);
-                match command::run_ps(&self.address, session, sudo, flags).await {
+                match command::run_ps(&self.address, session, docker, flags).await {
                     Ok(result) => Ok(result),
                     Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
                 }
             }
             InternalCommand::Restart { time, container_id } => {
-                match command::run_restart(&self.address, session, sudo, time, &container_id).await
+                match command::run_restart(&self.address, session, docker, time, &container_id)
+                    .await
                 {
                     Ok(result) => Ok(result),
                     Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
@@ -173,7 +528,24 @@ impl Node {
                 volumes,
             } => {
                 let flags = RmFlags::new(force, volumes);
-                match command::run_rm(&self.address, session, sudo, &container_id, flags).await {
+                match command::run_rm(&self.address, session, docker, &container_id, flags).await {
+                    Ok(result) => Ok(result),
+                    Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
+                }
+            }
+            InternalCommand::Run { args, detach } => {
+                let flags = RunFlags::new(args, detach);
+                match command::run_run(
+                    &self.address,
+                    session,
+                    docker,
+                    false,
+                    &args.image,
+                    &args.command,
+                    flags,
+                )
+                .await
+                {
                     Ok(result) => Ok(result),
                     Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
                 }
@@ -182,20 +554,52 @@ impl Node {
                 container_id,
                 attach,
             } => {
-                match command::run_start(&self.address, session, sudo, &container_id, attach).await
+                match command::run_start(&self.address, session, docker, &container_id, attach)
+                    .await
+                {
+                    Ok(result) => Ok(result),
+                    Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
+                }
+            }
+            InternalCommand::Stats {
+                container_id,
+                all,
+                no_trunc,
+            } => {
+                let flags = StatsFlags::new(all, no_trunc);
+                match command::run_stats(&self.address, session, docker, &container_id, flags).await
                 {
                     Ok(result) => Ok(result),
                     Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
                 }
             }
             InternalCommand::Stop { container_id } => {
-                match command::run_stop(&self.address, session, sudo, &container_id).await {
+                match command::run_stop(&self.address, session, docker, &container_id).await {
                     Ok(result) => Ok(result),
                     Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
                 }
             }
             InternalCommand::System(command) => {
-                match command::run_system(&self.address, session, sudo, command).await {
+                match command::run_system(&self.address, session, docker, command).await {
+                    Ok(result) => Ok(result),
+                    Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
+                }
+            }
+            InternalCommand::Top {
+                container_id,
+                options,
+            } => {
+                // there is exactly one container since docker only tops one at a time
+                let container_id = container_id.first().copied().unwrap_or_default();
+
+                match command::run_top(&self.address, session, docker, container_id, &options).await
+                {
+                    Ok(result) => Ok(result),
+                    Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
+                }
+            }
+            InternalCommand::Unpause { container_id } => {
+                match command::run_unpause(&self.address, session, docker, &container_id).await {
                     Ok(result) => Ok(result),
                     Err(e) => Err(NodeError::SessionError(self.address.clone(), e)),
                 }
@@ -204,21 +608,72 @@ impl Node {
     }
 }
 
+impl From<InventoryHost> for Node {
+    fn from(host: InventoryHost) -> Self {
+        Self {
+            address: host.ssh.alias,
+            hostname: host.ssh.hostname,
+            user: host.ssh.user,
+            port: host.ssh.port,
+            identity_file: host.ssh.identity_file,
+            // `ProxyJump none` explicitly disables jumping for a host
+            proxy_jump: host
+                .ssh
+                .proxy_jump
+                .filter(|jump| !jump.eq_ignore_ascii_case("none")),
+            groups: host.groups,
+            tags: host.tags,
+            overrides: host.overrides,
+        }
+    }
+}
+
+impl From<SshHost> for Node {
+    fn from(host: SshHost) -> Self {
+        // ssh applies the settings of the alias from its own config, so nothing is overridden
+        Self::new(host.alias)
+    }
+}
+
 pub enum NodeError {
     SessionError(String, openssh::Error),
+    Timeout(String, Duration),
+    /// Docker itself failed on the node, with what it printed to stderr
+    DockerError(String, String),
+}
+
+impl NodeError {
+    pub fn hostname(&self) -> &str {
+        match self {
+            Self::SessionError(hostname, _) => hostname,
+            Self::Timeout(hostname, _) => hostname,
+            Self::DockerError(hostname, _) => hostname,
+        }
+    }
 }
 
 impl std::fmt::Display for NodeError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         match self {
             Self::SessionError(hostname, e) => write!(f, "[NodeError] {}: {}", hostname, e),
+            Self::Timeout(hostname, timeout) => write!(
+                f,
+                "[NodeError] {}: timed out after {}s",
+                hostname,
+                timeout.as_secs_f64()
+            ),
+            Self::DockerError(hostname, stderr) => {
+                write!(f, "[NodeError] {}: {}", hostname, stderr)
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Client, Node};
+    use std::time::Duration;
+
+    use super::{Client, Node, NodeError, NodeOverrides, NodeSelector};
 
     #[test]
     fn test_from_config() {
@@ -254,6 +709,82 @@ mod tests {
     }
 
     #[test]
+    fn test_from_config_multiple_aliases() {
+        let client = Client::from_config("test_files/mock_ssh_config_full", Some("^web"));
+        let correct_nodes: Vec<(String, String)> = vec![
+            ("web1".into(), "web1".into()),
+            ("web2".into(), "web2".into()),
+        ];
+
+        let nodes: Vec<(String, String)> = client
+            .nodes
+            .iter()
+            .map(|node| (node.address.clone(), node.destination().to_string()))
+            .collect();
+
+        assert_eq!(correct_nodes, nodes);
+    }
+
+    #[test]
+    fn test_from_inventory_group() {
+        let groups: Vec<String> = vec!["staging".into()];
+        let selector = NodeSelector {
+            groups: &groups,
+            ..Default::default()
+        };
+        let client = Client::from_inventory(
+            "test_files/mock_inventory.toml",
+            "test_files/mock_ssh_config",
+            &selector,
+        )
+        .unwrap();
+        let correct_nodes: Vec<String> = vec!["stage1".into(), "web2".into()];
+
+        let nodes: Vec<String> = client
+            .nodes
+            .iter()
+            .map(|node| node.address.clone())
+            .collect();
+
+        assert_eq!(correct_nodes, nodes);
+    }
+
+    #[test]
+    fn test_from_inventory_tag_and_regex() {
+        let tags: Vec<String> = vec!["nginx".into()];
+        let selector = NodeSelector {
+            regex: Some("web"),
+            tags: &tags,
+            ..Default::default()
+        };
+        let client = Client::from_inventory(
+            "test_files/mock_inventory.toml",
+            "test_files/mock_ssh_config",
+            &selector,
+        )
+        .unwrap();
+
+        let nodes: Vec<(String, NodeOverrides)> = client
+            .nodes
+            .iter()
+            .map(|node| (node.address.clone(), node.overrides.clone()))
+            .collect();
+
+        assert_eq!(
+            vec![(
+                String::from("web1"),
+                NodeOverrides {
+                    sudo: Some(true),
+                    identity_file: Some("~/.ssh/prod".into()),
+                    docker: Some("/usr/local/bin/docker".into()),
+                }
+            )],
+            nodes
+        );
+    }
+
+    #[test]
+    #[allow(clippy::explicit_counter_loop)]
     fn test_client_info() {
         let client = Client::from_config("test_files/mock_ssh_config", Some(".*"));
         let correct_nodes: Vec<(String, Node)> = vec![
@@ -277,4 +808,14 @@ mod tests {
             index += 1;
         }
     }
+
+    #[test]
+    fn test_node_error_timeout_display() {
+        let error = NodeError::Timeout("abc".into(), Duration::from_millis(2500));
+
+        assert_eq!(
+            "[NodeError] abc: timed out after 2.5s",
+            format!("{}", error)
+        );
+    }
 }
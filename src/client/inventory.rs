@@ -0,0 +1,243 @@
+//! Parser for the docker-manager inventory file.
+//!
+//! The inventory lists nodes by group and optionally carries per-node settings:
+//!
+//! ```toml
+//! [groups]
+//! prod = ["web1", "web2"]
+//! staging = ["stage1"]
+//!
+//! [nodes.web1]
+//! hostname = "10.0.0.1"
+//! tags = ["nginx"]
+//! sudo = true
+//! identity_file = "~/.ssh/prod"
+//! docker = "/usr/local/bin/docker"
+//! ```
+//!
+//! Node names are ssh destinations, so ssh applies its own settings from `~/.ssh/config` for the
+//! alias. When the inventory overrides the `hostname`, the settings for the alias are used as a
+//! base that the inventory settings override. `sudo`, `identity_file` and `docker` also take
+//! precedence over the global flags.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::connector::NodeOverrides;
+use super::ssh_config::SshHost;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Inventory {
+    #[serde(default)]
+    groups: BTreeMap<String, Vec<String>>,
+
+    #[serde(default)]
+    nodes: BTreeMap<String, InventoryNode>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct InventoryNode {
+    pub hostname: Option<String>,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    pub identity_file: Option<String>,
+    pub proxy_jump: Option<String>,
+
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Overrides the global sudo flag for this node
+    pub sudo: Option<bool>,
+
+    /// Path to the docker binary on this node
+    pub docker: Option<String>,
+}
+
+/// A node from the inventory with the ssh config settings for its alias merged in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InventoryHost {
+    pub ssh: SshHost,
+    pub groups: Vec<String>,
+    pub tags: Vec<String>,
+    pub overrides: NodeOverrides,
+}
+
+#[derive(Debug)]
+pub enum InventoryError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+}
+
+impl std::fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Inventory {
+    pub fn from_file<A: AsRef<Path>>(path: A) -> Result<Self, InventoryError> {
+        let file_contents = std::fs::read_to_string(path).map_err(InventoryError::Io)?;
+
+        toml::from_str(&file_contents).map_err(InventoryError::Parse)
+    }
+
+    /// Returns every node in the inventory, sorted by name. `ssh_hosts` is used to fill in the
+    /// connection settings that the inventory does not override.
+    pub fn hosts(&self, ssh_hosts: &[SshHost]) -> Vec<InventoryHost> {
+        let mut names: Vec<&String> = self.nodes.keys().collect();
+        for members in self.groups.values() {
+            names.extend(members);
+        }
+        names.sort();
+        names.dedup();
+
+        names
+            .into_iter()
+            .map(|name| {
+                let node = self.nodes.get(name).cloned().unwrap_or_default();
+                // ssh applies its own config when it connects to the alias, including the parts
+                // the parser skips like `Match` blocks. The parsed settings are only needed when
+                // the inventory points the node at another `HostName`.
+                let base = match node.hostname {
+                    Some(_) => ssh_hosts
+                        .iter()
+                        .find(|host| &host.alias == name)
+                        .cloned()
+                        .unwrap_or_default(),
+                    None => SshHost::default(),
+                };
+
+                InventoryHost {
+                    ssh: SshHost {
+                        alias: name.clone(),
+                        hostname: node.hostname,
+                        user: node.user.or(base.user),
+                        port: node.port.or(base.port),
+                        identity_file: base.identity_file,
+                        proxy_jump: node.proxy_jump.or(base.proxy_jump),
+                    },
+                    groups: self
+                        .groups
+                        .iter()
+                        .filter(|(_, members)| members.contains(name))
+                        .map(|(group, _)| group.clone())
+                        .collect(),
+                    tags: node.tags,
+                    overrides: NodeOverrides {
+                        sudo: node.sudo,
+                        identity_file: node.identity_file,
+                        docker: node.docker,
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Inventory, InventoryHost, NodeOverrides, SshHost};
+
+    #[test]
+    fn test_hosts() {
+        let inventory = Inventory::from_file("test_files/mock_inventory.toml").unwrap();
+        let ssh_hosts = vec![SshHost {
+            alias: "web1".into(),
+            hostname: Some("10.0.0.1".into()),
+            user: Some("deploy".into()),
+            port: Some(2222),
+            identity_file: Some("~/.ssh/web".into()),
+            proxy_jump: None,
+        }];
+
+        let correct_hosts: Vec<InventoryHost> = vec![
+            InventoryHost {
+                ssh: SshHost {
+                    alias: "gpu1".into(),
+                    ..Default::default()
+                },
+                groups: vec![],
+                tags: vec!["gpu".into()],
+                overrides: NodeOverrides::default(),
+            },
+            InventoryHost {
+                ssh: SshHost {
+                    alias: "stage1".into(),
+                    ..Default::default()
+                },
+                groups: vec!["staging".into()],
+                tags: vec![],
+                overrides: NodeOverrides::default(),
+            },
+            InventoryHost {
+                ssh: SshHost {
+                    alias: "web1".into(),
+                    hostname: None,
+                    user: Some("root".into()),
+                    port: None,
+                    identity_file: None,
+                    proxy_jump: None,
+                },
+                groups: vec!["prod".into()],
+                tags: vec!["nginx".into()],
+                overrides: NodeOverrides {
+                    sudo: Some(true),
+                    identity_file: Some("~/.ssh/prod".into()),
+                    docker: Some("/usr/local/bin/docker".into()),
+                },
+            },
+            InventoryHost {
+                ssh: SshHost {
+                    alias: "web2".into(),
+                    ..Default::default()
+                },
+                groups: vec!["prod".into(), "staging".into()],
+                tags: vec![],
+                overrides: NodeOverrides::default(),
+            },
+        ];
+
+        assert_eq!(correct_hosts, inventory.hosts(&ssh_hosts));
+    }
+
+    #[test]
+    fn test_hosts_hostname_override() {
+        let inventory =
+            toml::from_str::<Inventory>("[nodes.web1]\nhostname = \"10.0.0.9\"\nuser = \"root\"\n")
+                .unwrap();
+        let ssh_hosts = vec![SshHost {
+            alias: "web1".into(),
+            hostname: Some("10.0.0.1".into()),
+            user: Some("deploy".into()),
+            port: Some(2222),
+            identity_file: Some("~/.ssh/web".into()),
+            proxy_jump: None,
+        }];
+
+        assert_eq!(
+            SshHost {
+                alias: "web1".into(),
+                hostname: Some("10.0.0.9".into()),
+                user: Some("root".into()),
+                port: Some(2222),
+                identity_file: Some("~/.ssh/web".into()),
+                proxy_jump: None,
+            },
+            inventory.hosts(&ssh_hosts)[0].ssh
+        );
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let inventory = toml::from_str::<Inventory>("[nodes.web1]\nsudoo = true\n");
+
+        assert!(inventory.is_err());
+    }
+}
@@ -1,3 +1,7 @@
 mod connector;
+mod inventory;
+mod ssh_config;
 
-pub use connector::{Client, Node, NodeError};
+pub use connector::{Client, Node, NodeError, NodeSelector, RunOptions};
+pub use inventory::InventoryError;
+pub use ssh_config::pattern_match;
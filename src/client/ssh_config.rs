@@ -0,0 +1,425 @@
+//! Parser for OpenSSH client configuration files (`~/.ssh/config`).
+//!
+//! Only the subset of `ssh_config(5)` that is needed to build a node list is understood:
+//! `Host` and `Match` blocks, `Include` directives and the `HostName`, `User`, `Port`,
+//! `IdentityFile` and `ProxyJump` options. Every other option is ignored.
+
+use std::path::{Path, PathBuf};
+
+use crate::utility::{expand_tilde, home_dir};
+
+/// OpenSSH refuses to follow more than 16 levels of nested `Include` directives.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// A concrete host alias found in the ssh config together with its resolved options.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SshHost {
+    pub alias: String,
+    pub hostname: Option<String>,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    pub identity_file: Option<String>,
+    pub proxy_jump: Option<String>,
+}
+
+/// The condition that decides if the options in a block apply to a host.
+#[derive(Debug)]
+enum Criteria {
+    /// Options that appear before the first `Host` or `Match` line apply to every host.
+    Global,
+    Host(Vec<String>),
+    Match(Vec<String>),
+}
+
+#[derive(Debug)]
+struct Block {
+    criteria: Criteria,
+    options: Vec<(String, String)>,
+}
+
+/// Parses the ssh config at `path` and returns every concrete host alias in the order they are
+/// declared. Patterns containing wildcards or negations only contribute options to the hosts
+/// they match and never show up as hosts themselves.
+pub fn parse<A: AsRef<Path>>(path: A) -> Vec<SshHost> {
+    parse_with_include_dir(path, home_dir().join(".ssh"))
+}
+
+/// Like `parse`, relative `Include` paths are resolved against `include_dir` instead of `~/.ssh`.
+fn parse_with_include_dir<A: AsRef<Path>, B: AsRef<Path>>(path: A, include_dir: B) -> Vec<SshHost> {
+    let mut blocks: Vec<Block> = vec![Block {
+        criteria: Criteria::Global,
+        options: vec![],
+    }];
+    read_blocks(path.as_ref(), include_dir.as_ref(), &mut blocks, 0);
+
+    let mut aliases: Vec<String> = vec![];
+    for block in &blocks {
+        if let Criteria::Host(patterns) = &block.criteria {
+            for pattern in patterns {
+                if !is_pattern(pattern) && !aliases.contains(pattern) {
+                    aliases.push(pattern.clone());
+                }
+            }
+        }
+    }
+
+    aliases
+        .into_iter()
+        .map(|alias| resolve(&blocks, alias))
+        .collect()
+}
+
+fn read_blocks(path: &Path, include_dir: &Path, blocks: &mut Vec<Block>, depth: usize) {
+    if depth > MAX_INCLUDE_DEPTH {
+        return;
+    }
+
+    let file_contents = std::fs::read_to_string(path).unwrap_or_default();
+
+    for line in file_contents.lines() {
+        let (keyword, args) = match split_line(line) {
+            Some(split) => split,
+            None => continue,
+        };
+
+        match keyword.as_str() {
+            "host" => blocks.push(Block {
+                criteria: Criteria::Host(args),
+                options: vec![],
+            }),
+            "match" => blocks.push(Block {
+                criteria: Criteria::Match(args),
+                options: vec![],
+            }),
+            // Included files are spliced in place, which gives the same semantics as OpenSSH:
+            // options before the first `Host` line in the included file belong to the
+            // enclosing block.
+            "include" => {
+                for arg in &args {
+                    for include in expand_include(include_dir, arg) {
+                        read_blocks(&include, include_dir, blocks, depth + 1);
+                    }
+                }
+            }
+            _ => {
+                if let (Some(block), Some(value)) = (blocks.last_mut(), args.into_iter().next()) {
+                    block.options.push((keyword, value));
+                }
+            }
+        }
+    }
+}
+
+/// Splits a config line into a lowercase keyword and its arguments. Both `Key value` and
+/// `Key=value` are accepted and arguments may be double quoted. Returns `None` for blank
+/// lines and comments.
+fn split_line(line: &str) -> Option<(String, Vec<String>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (keyword, rest) = match line.find(|c: char| c.is_whitespace() || c == '=') {
+        Some(index) => (&line[..index], &line[index..]),
+        None => (line, ""),
+    };
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix('=').unwrap_or(rest);
+
+    let mut args: Vec<String> = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in rest.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+
+    Some((keyword.to_lowercase(), args))
+}
+
+/// Resolves an `Include` argument to the files it refers to. Like OpenSSH does for the user
+/// config, relative paths are resolved against `include_dir` (`~/.ssh`) no matter which file
+/// includes them. `~` is expanded to the home directory and a wildcard is allowed in the file
+/// name.
+fn expand_include(include_dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let path = expand_tilde(pattern);
+    let path = match path.is_absolute() {
+        true => path,
+        false => include_dir.join(path),
+    };
+
+    let file_name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name.to_string(),
+        None => return vec![],
+    };
+
+    if !is_pattern(&file_name) {
+        return vec![path];
+    }
+
+    let directory = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut rv: Vec<PathBuf> = match std::fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| pattern_match(&file_name, name))
+                    .unwrap_or(false)
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+    // OpenSSH reads glob matches in lexical order
+    rv.sort();
+
+    rv
+}
+
+fn resolve(blocks: &[Block], alias: String) -> SshHost {
+    let mut host = SshHost {
+        alias,
+        ..Default::default()
+    };
+
+    for block in blocks.iter().filter(|block| applies(block, &host.alias)) {
+        // The first obtained value for each option is the one that is used.
+        for (keyword, value) in &block.options {
+            match keyword.as_str() {
+                "hostname" if host.hostname.is_none() => {
+                    host.hostname = Some(expand_tokens(value, &host.alias))
+                }
+                "user" if host.user.is_none() => host.user = Some(value.clone()),
+                "port" if host.port.is_none() => host.port = value.parse().ok(),
+                "identityfile" if host.identity_file.is_none() => {
+                    host.identity_file = Some(value.clone())
+                }
+                "proxyjump" if host.proxy_jump.is_none() => host.proxy_jump = Some(value.clone()),
+                _ => (),
+            }
+        }
+    }
+
+    host
+}
+
+fn applies(block: &Block, alias: &str) -> bool {
+    match &block.criteria {
+        Criteria::Global => true,
+        Criteria::Host(patterns) => patterns_match(patterns, alias),
+        Criteria::Match(criteria) => match_criteria(criteria, alias),
+    }
+}
+
+/// Evaluates the criteria of a `Match` line. Only `all`, `host` and `originalhost` can be
+/// evaluated without connecting, blocks using any other criteria are assumed not to apply.
+fn match_criteria(criteria: &[String], alias: &str) -> bool {
+    let mut iter = criteria.iter();
+    let mut matched = false;
+
+    while let Some(criterion) = iter.next() {
+        match criterion.to_lowercase().as_str() {
+            "all" => matched = true,
+            "host" | "originalhost" => {
+                let patterns: Vec<String> = match iter.next() {
+                    Some(list) => list.split(',').map(String::from).collect(),
+                    None => return false,
+                };
+                if !patterns_match(&patterns, alias) {
+                    return false;
+                }
+                matched = true;
+            }
+            _ => return false,
+        }
+    }
+
+    matched
+}
+
+/// A host matches a pattern list when it matches at least one pattern and none of the negated
+/// (`!pattern`) ones.
+fn patterns_match(patterns: &[String], alias: &str) -> bool {
+    let mut matched = false;
+
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(negated) => {
+                if pattern_match(negated, alias) {
+                    return false;
+                }
+            }
+            None => {
+                if pattern_match(pattern, alias) {
+                    matched = true;
+                }
+            }
+        }
+    }
+
+    matched
+}
+
+fn is_pattern(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '!'])
+}
+
+/// Matches `value` against a pattern where `*` matches any number of characters and `?`
+/// matches exactly one.
+pub fn pattern_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+
+    let (mut p, mut v) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, v));
+            p += 1;
+        } else if let Some((star_p, star_v)) = star {
+            p = star_p + 1;
+            v = star_v + 1;
+            star = Some((star_p, star_v + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Expands the `%h` and `%%` tokens that are allowed in `HostName`.
+fn expand_tokens(value: &str, alias: &str) -> String {
+    value
+        .replace("%%", "\0")
+        .replace("%h", alias)
+        .replace('\0', "%")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{
+        expand_include, parse, parse_with_include_dir, pattern_match, split_line, SshHost,
+    };
+
+    #[test]
+    fn test_parse() {
+        let hosts = parse_with_include_dir("test_files/mock_ssh_config_full", "test_files");
+
+        let correct_hosts: Vec<SshHost> = vec![
+            SshHost {
+                alias: "web1".into(),
+                hostname: Some("10.0.0.1".into()),
+                user: Some("deploy".into()),
+                port: Some(2222),
+                identity_file: Some("~/.ssh/web".into()),
+                proxy_jump: Some("bastion".into()),
+            },
+            SshHost {
+                alias: "web2".into(),
+                hostname: Some("10.0.0.1".into()),
+                user: Some("deploy".into()),
+                port: Some(2222),
+                identity_file: Some("~/.ssh/web".into()),
+                proxy_jump: Some("bastion".into()),
+            },
+            SshHost {
+                alias: "db.prod".into(),
+                hostname: Some("db.prod.example.com".into()),
+                user: Some("admin".into()),
+                port: None,
+                identity_file: Some("~/.ssh/id_default".into()),
+                proxy_jump: Some("bastion".into()),
+            },
+            SshHost {
+                alias: "bastion".into(),
+                hostname: Some("203.0.113.7".into()),
+                user: Some("jump".into()),
+                port: None,
+                identity_file: Some("~/.ssh/id_default".into()),
+                proxy_jump: None,
+            },
+            SshHost {
+                alias: "included1".into(),
+                hostname: Some("192.168.1.10".into()),
+                user: Some("ops".into()),
+                port: None,
+                identity_file: Some("~/.ssh/id_default".into()),
+                proxy_jump: None,
+            },
+            SshHost {
+                alias: "included2".into(),
+                hostname: None,
+                user: Some("ops".into()),
+                port: None,
+                identity_file: Some("~/.ssh/id_default".into()),
+                proxy_jump: None,
+            },
+        ];
+
+        assert_eq!(correct_hosts, hosts);
+    }
+
+    #[test]
+    fn test_parse_missing_file() {
+        assert_eq!(parse("test_files/does_not_exist"), vec![]);
+    }
+
+    #[test]
+    fn test_split_line() {
+        assert_eq!(
+            split_line("  HostName=10.0.0.1"),
+            Some(("hostname".into(), vec!["10.0.0.1".into()]))
+        );
+        assert_eq!(
+            split_line("IdentityFile \"~/my keys/id\""),
+            Some(("identityfile".into(), vec!["~/my keys/id".into()]))
+        );
+        assert_eq!(
+            split_line("Host a b  c"),
+            Some(("host".into(), vec!["a".into(), "b".into(), "c".into()]))
+        );
+        assert_eq!(split_line("   # comment"), None);
+        assert_eq!(split_line(""), None);
+    }
+
+    #[test]
+    fn test_pattern_match() {
+        assert!(pattern_match("*", "anything"));
+        assert!(pattern_match("*.prod", "db.prod"));
+        assert!(pattern_match("web?", "web1"));
+        assert!(pattern_match("a*b*c", "aXXbYYc"));
+        assert!(!pattern_match("*.prod", "db.staging"));
+        assert!(!pattern_match("web?", "web10"));
+    }
+
+    #[test]
+    fn test_expand_include() {
+        assert_eq!(
+            vec![PathBuf::from("/home/user/.ssh/config.d/work")],
+            expand_include(Path::new("/home/user/.ssh"), "config.d/work")
+        );
+        assert_eq!(
+            vec![PathBuf::from("/etc/ssh/extra")],
+            expand_include(Path::new("/home/user/.ssh"), "/etc/ssh/extra")
+        );
+    }
+}
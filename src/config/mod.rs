@@ -0,0 +1,5 @@
+//! This module contains the user configuration that provides defaults for the global flags
+
+mod settings;
+
+pub use settings::*;
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::cli::App;
+use crate::formatter::OutputFormat;
+use crate::utility::config_dir;
+
+const ENV_PREFIX: &str = "DOCKER_MANAGER_";
+
+/// Defaults for the global flags, these can be set in the config file, in a profile or through
+/// `DOCKER_MANAGER_*` environment variables.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub sudo: Option<bool>,
+    pub identity_file: Option<String>,
+    pub regex: Option<String>,
+    pub inventory: Option<String>,
+    pub group: Option<Vec<String>>,
+    pub tag: Option<Vec<String>>,
+    pub concurrency: Option<usize>,
+    pub connect_timeout: Option<u64>,
+    pub timeout: Option<u64>,
+    pub output: Option<OutputFormat>,
+}
+
+impl Settings {
+    /// Reads the settings from environment variables, `lookup` returns the value of a variable
+    /// if it is set.
+    pub fn from_env<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<Self, ConfigError> {
+        let var = |name: &str| lookup(&format!("{}{}", ENV_PREFIX, name));
+        let list = |name: &str| {
+            var(name).map(|value| {
+                value
+                    .split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect()
+            })
+        };
+
+        let number = |name: &str| match var(name) {
+            Some(value) => match value.parse::<u64>() {
+                Ok(number) => Ok(Some(number)),
+                Err(_) => Err(ConfigError::InvalidEnv(
+                    format!("{}{}", ENV_PREFIX, name),
+                    value,
+                )),
+            },
+            None => Ok(None),
+        };
+
+        let sudo = match var("SUDO") {
+            Some(value) => match value.to_lowercase().as_str() {
+                "1" | "true" | "yes" => Some(true),
+                "0" | "false" | "no" => Some(false),
+                // an empty variable is treated as unset, `false` would mean never
+                "" => None,
+                _ => {
+                    return Err(ConfigError::InvalidEnv(
+                        format!("{}SUDO", ENV_PREFIX),
+                        value,
+                    ))
+                }
+            },
+            None => None,
+        };
+
+        let output = match var("OUTPUT") {
+            Some(value) => match value.parse::<OutputFormat>() {
+                Ok(output) => Some(output),
+                Err(_) => {
+                    return Err(ConfigError::InvalidEnv(
+                        format!("{}OUTPUT", ENV_PREFIX),
+                        value,
+                    ))
+                }
+            },
+            None => None,
+        };
+
+        Ok(Self {
+            sudo,
+            identity_file: var("IDENTITY_FILE"),
+            regex: var("REGEX"),
+            inventory: var("INVENTORY"),
+            group: list("GROUP"),
+            tag: list("TAG"),
+            concurrency: number("CONCURRENCY")?.map(|n| n as usize),
+            connect_timeout: number("CONNECT_TIMEOUT")?,
+            timeout: number("TIMEOUT")?,
+            output,
+        })
+    }
+
+    /// Returns these settings with every value that is set in `other` replaced.
+    pub fn merge(self, other: Settings) -> Self {
+        Self {
+            sudo: other.sudo.or(self.sudo),
+            identity_file: other.identity_file.or(self.identity_file),
+            regex: other.regex.or(self.regex),
+            inventory: other.inventory.or(self.inventory),
+            group: other.group.or(self.group),
+            tag: other.tag.or(self.tag),
+            concurrency: other.concurrency.or(self.concurrency),
+            connect_timeout: other.connect_timeout.or(self.connect_timeout),
+            timeout: other.timeout.or(self.timeout),
+            output: other.output.or(self.output),
+        }
+    }
+
+    /// Fills in every flag that was not passed on the command line.
+    pub fn apply(self, app: &mut App) {
+        // `sudo = false` means never, like `--no-sudo` and the inventory do
+        if !app.sudo && !app.no_sudo {
+            match self.sudo {
+                Some(true) => app.sudo = true,
+                Some(false) => app.no_sudo = true,
+                None => {}
+            }
+        }
+
+        if app.identity_file.is_none() {
+            app.identity_file = self.identity_file;
+        }
+
+        if app.regex.is_none() {
+            app.regex = self.regex;
+        }
+
+        if app.inventory.is_none() {
+            app.inventory = self.inventory;
+        }
+
+        if app.group.is_empty() {
+            app.group = self.group.unwrap_or_default();
+        }
+
+        if app.tag.is_empty() {
+            app.tag = self.tag.unwrap_or_default();
+        }
+
+        if app.concurrency.is_none() {
+            app.concurrency = self.concurrency;
+        }
+
+        if app.connect_timeout.is_none() {
+            app.connect_timeout = self.connect_timeout;
+        }
+
+        if app.timeout.is_none() {
+            app.timeout = self.timeout;
+        }
+
+        if app.output.is_none() {
+            app.output = self.output;
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    default: Settings,
+
+    #[serde(default)]
+    profiles: HashMap<String, Settings>,
+}
+
+impl Config {
+    pub fn from_file<A: AsRef<Path>>(path: A) -> Result<Self, ConfigError> {
+        let file_contents = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+
+        toml::from_str(&file_contents).map_err(ConfigError::Parse)
+    }
+
+    /// Returns the default settings with the given profile applied on top.
+    pub fn settings(&self, profile: Option<&str>) -> Result<Settings, ConfigError> {
+        match profile {
+            Some(name) => match self.profiles.get(name) {
+                Some(settings) => Ok(self.default.clone().merge(settings.clone())),
+                None => Err(ConfigError::UnknownProfile(name.to_string())),
+            },
+            None => Ok(self.default.clone()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    UnknownProfile(String),
+    InvalidEnv(String, String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse(e) => write!(f, "{}", e),
+            Self::UnknownProfile(name) => write!(f, "Profile {} is not defined", name),
+            Self::InvalidEnv(name, value) => write!(f, "Invalid value for {}: {}", name, value),
+        }
+    }
+}
+
+/// Returns the path of the config file, `DOCKER_MANAGER_CONFIG` overrides the default location.
+pub fn config_path() -> PathBuf {
+    match std::env::var(format!("{}CONFIG", ENV_PREFIX)) {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => config_dir().join("config.toml"),
+    }
+}
+
+/// Merges the config file, the selected profile and the environment into `app`. Flags given on
+/// the command line always win, then environment variables, then the profile and finally the
+/// defaults from the config file.
+pub fn apply(app: &mut App) {
+    let path = config_path();
+    let profile = app
+        .profile
+        .clone()
+        .or_else(|| std::env::var(format!("{}PROFILE", ENV_PREFIX)).ok());
+
+    let config = match path.exists() {
+        true => Config::from_file(&path),
+        false => Ok(Config::default()),
+    };
+
+    let settings = config
+        .and_then(|config| config.settings(profile.as_deref()))
+        .and_then(|settings| {
+            Settings::from_env(|name| std::env::var(name).ok()).map(|env| settings.merge(env))
+        });
+
+    match settings {
+        Ok(settings) => settings.apply(app),
+        Err(e) => {
+            eprintln!("{}", error_message(&e, &path));
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Describes `error` together with where it came from, only reading or parsing `path` is an
+/// error of the config file itself.
+fn error_message(error: &ConfigError, path: &Path) -> String {
+    match error {
+        ConfigError::Io(_) | ConfigError::Parse(_) => {
+            format!(
+                "Could not load the config file {}\n{}",
+                path.display(),
+                error
+            )
+        }
+        ConfigError::UnknownProfile(_) => {
+            format!("{} in the config file {}", error, path.display())
+        }
+        ConfigError::InvalidEnv(_, _) => format!("{}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use clap::Parser;
+
+    use super::{error_message, App, Config, ConfigError, OutputFormat, Settings};
+
+    #[test]
+    fn test_profile_settings() {
+        let config = Config::from_file("test_files/mock_config.toml").unwrap();
+
+        let correct_settings = Settings {
+            sudo: Some(false),
+            identity_file: Some("~/.ssh/id_default".into()),
+            regex: Some(".*".into()),
+            inventory: Some("~/prod-inventory.toml".into()),
+            group: None,
+            tag: Some(vec!["nginx".into(), "public".into()]),
+            concurrency: Some(20),
+            connect_timeout: Some(5),
+            timeout: None,
+            output: Some(OutputFormat::Json),
+        };
+
+        assert_eq!(correct_settings, config.settings(Some("prod")).unwrap());
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let config = Config::from_file("test_files/mock_config.toml").unwrap();
+
+        assert!(matches!(
+            config.settings(Some("does_not_exist")),
+            Err(ConfigError::UnknownProfile(_))
+        ));
+    }
+
+    #[test]
+    fn test_env_overrides_profile() {
+        let config = Config::from_file("test_files/mock_config.toml").unwrap();
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("DOCKER_MANAGER_SUDO", "no"),
+            ("DOCKER_MANAGER_GROUP", "staging, prod"),
+            ("DOCKER_MANAGER_TIMEOUT", "30"),
+            ("DOCKER_MANAGER_OUTPUT", "jsonl"),
+        ]);
+
+        let settings = config.settings(Some("staging")).unwrap().merge(
+            Settings::from_env(|name| env.get(name).map(|value| value.to_string())).unwrap(),
+        );
+
+        let correct_settings = Settings {
+            sudo: Some(false),
+            identity_file: Some("~/.ssh/id_default".into()),
+            regex: Some("^stage".into()),
+            inventory: None,
+            group: Some(vec!["staging".into(), "prod".into()]),
+            tag: None,
+            concurrency: Some(20),
+            connect_timeout: None,
+            timeout: Some(30),
+            output: Some(OutputFormat::Jsonl),
+        };
+
+        assert_eq!(correct_settings, settings);
+    }
+
+    #[test]
+    fn test_invalid_env() {
+        let settings = Settings::from_env(|name| match name {
+            "DOCKER_MANAGER_SUDO" => Some("maybe".into()),
+            _ => None,
+        });
+
+        assert!(matches!(settings, Err(ConfigError::InvalidEnv(_, _))));
+
+        let settings = Settings::from_env(|name| match name {
+            "DOCKER_MANAGER_CONNECT_TIMEOUT" => Some("5s".into()),
+            _ => None,
+        });
+
+        assert!(matches!(settings, Err(ConfigError::InvalidEnv(_, _))));
+    }
+
+    #[test]
+    fn test_no_sudo_overrides_config() {
+        let settings = Settings {
+            sudo: Some(true),
+            ..Default::default()
+        };
+
+        let mut app = App::try_parse_from(["docker-manager", "ps"]).unwrap();
+        settings.clone().apply(&mut app);
+        assert!(app.sudo);
+
+        let mut app = App::try_parse_from(["docker-manager", "--no-sudo", "ps"]).unwrap();
+        settings.apply(&mut app);
+        assert!(!app.sudo);
+    }
+
+    #[test]
+    fn test_sudo_false_means_never() {
+        let settings = Settings {
+            sudo: Some(false),
+            ..Default::default()
+        };
+
+        let mut app = App::try_parse_from(["docker-manager", "ps"]).unwrap();
+        settings.clone().apply(&mut app);
+        assert!(!app.sudo);
+        assert!(app.no_sudo);
+
+        let mut app = App::try_parse_from(["docker-manager", "--sudo", "ps"]).unwrap();
+        settings.apply(&mut app);
+        assert!(app.sudo);
+        assert!(!app.no_sudo);
+    }
+
+    #[test]
+    fn test_error_message() {
+        let path = Path::new("config.toml");
+
+        assert_eq!(
+            "Profile staging is not defined in the config file config.toml",
+            error_message(&ConfigError::UnknownProfile("staging".into()), path)
+        );
+        assert_eq!(
+            "Invalid value for DOCKER_MANAGER_SUDO: maybe",
+            error_message(
+                &ConfigError::InvalidEnv("DOCKER_MANAGER_SUDO".into(), "maybe".into()),
+                path
+            )
+        );
+    }
+}
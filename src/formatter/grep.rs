@@ -0,0 +1,238 @@
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::utility::NodeOutput;
+
+/// Printed between lines that are not next to each other, like grep does.
+const SEPARATOR: &str = "--";
+
+/// Searches the captured logs of containers for lines matching a regular expression.
+#[derive(Debug)]
+pub struct LogGrep {
+    regex: Regex,
+    /// Number of lines shown before and after every matching line
+    context: usize,
+}
+
+/// A line of the logs of a container that matched, or that is shown as context of a match.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GrepLine<'a> {
+    /// Number of the line in the stream it was printed to, starting at 1
+    pub number: usize,
+    pub line: &'a str,
+    pub stderr: bool,
+    pub matched: bool,
+}
+
+impl LogGrep {
+    pub fn new(pattern: &str, context: usize) -> Self {
+        match Regex::new(pattern) {
+            Ok(regex) => Self { regex, context },
+            Err(e) => {
+                eprintln!(
+                    "Some error has occured while compiling your regex patterns {}\n{}",
+                    pattern, e
+                );
+                std::process::exit(1)
+            }
+        }
+    }
+
+    /// Returns the matching lines of `text` together with their context lines, in order.
+    pub fn search<'t>(&self, text: &'t str, stderr: bool) -> Vec<GrepLine<'t>> {
+        let lines: Vec<&str> = text.lines().collect();
+        // `None` for lines that are left out, otherwise whether the line matched
+        let mut shown: Vec<Option<bool>> = vec![None; lines.len()];
+
+        for (index, line) in lines.iter().enumerate() {
+            if !self.regex.is_match(line) {
+                continue;
+            }
+
+            let end = (index + self.context).min(lines.len() - 1);
+            for context in &mut shown[index.saturating_sub(self.context)..=end] {
+                context.get_or_insert(false);
+            }
+            shown[index] = Some(true);
+        }
+
+        lines
+            .iter()
+            .zip(shown)
+            .enumerate()
+            .filter_map(|(index, (line, shown))| {
+                shown.map(|matched| GrepLine {
+                    number: index + 1,
+                    line,
+                    stderr,
+                    matched,
+                })
+            })
+            .collect()
+    }
+
+    /// Searches both the stdout and the stderr of a `logs` command.
+    pub fn search_output<'o>(&self, output: &'o NodeOutput) -> Vec<GrepLine<'o>> {
+        let mut lines = self.search(&output.stdout, false);
+        lines.extend(self.search(&output.stderr, true));
+
+        lines
+    }
+
+    /// The search result of a single container as JSON rows.
+    pub fn rows(&self, output: &NodeOutput) -> Vec<Value> {
+        self.search_output(output)
+            .iter()
+            .map(|line| {
+                json!({
+                    "line_number": line.number,
+                    "line": line.line,
+                    "stderr": line.stderr,
+                    "match": line.matched,
+                })
+            })
+            .collect()
+    }
+
+    /// Formats the lines of a single container the way `grep -n` does, matches are followed by
+    /// a `:` and context lines by a `-`. Matches are highlighted when `color` is set.
+    pub fn format(&self, lines: &[GrepLine], color: bool) -> Vec<String> {
+        let mut rv: Vec<String> = vec![];
+        let mut previous: Option<&GrepLine> = None;
+
+        for line in lines {
+            if let Some(previous) = previous {
+                if previous.stderr != line.stderr || previous.number + 1 != line.number {
+                    rv.push(SEPARATOR.to_string());
+                }
+            }
+
+            let text = match color && line.matched {
+                true => self
+                    .regex
+                    .replace_all(line.line, "\x1b[1;31m$0\x1b[0m")
+                    .to_string(),
+                false => line.line.to_string(),
+            };
+            let delimiter = match line.matched {
+                true => ':',
+                false => '-',
+            };
+            rv.push(format!("{}{}{}", line.number, delimiter, text));
+
+            previous = Some(line);
+        }
+
+        rv
+    }
+}
+
+/// Prints the matching lines of the captured logs grouped by container, together with the
+/// number of matches. Containers without any match are left out, containers whose logs could
+/// not be fetched are reported as failed instead of searched. `outputs` need to have their
+/// container set.
+pub fn print_grep(outputs: &[&NodeOutput], grep: &LogGrep, color: bool) {
+    let mut total = 0;
+    let mut containers = 0;
+
+    let (outputs, failed): (Vec<&NodeOutput>, Vec<&NodeOutput>) =
+        outputs.iter().partition(|output| output.success());
+    for output in failed {
+        // the stderr of a failed `docker logs` is its error, not the logs of the container
+        eprintln!(
+            "{}/{}: {}",
+            output.node,
+            output.container.as_deref().unwrap_or_default(),
+            output.stderr.trim_end()
+        );
+    }
+
+    for output in &outputs {
+        let lines = grep.search_output(output);
+        let count = lines.iter().filter(|line| line.matched).count();
+        if count == 0 {
+            continue;
+        }
+
+        total += count;
+        containers += 1;
+
+        println!(
+            "{}/{}: {}",
+            output.node,
+            output.container.as_deref().unwrap_or_default(),
+            matches(count)
+        );
+        for line in grep.format(&lines, color) {
+            println!("{}", line);
+        }
+        println!();
+    }
+
+    println!(
+        "{} in {} of {} containers",
+        matches(total),
+        containers,
+        outputs.len()
+    );
+}
+
+fn matches(count: usize) -> String {
+    match count {
+        1 => "1 match".to_string(),
+        _ => format!("{} matches", count),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GrepLine, LogGrep};
+
+    const LOGS: &str = "starting\nlistening on :80\nerror: connection reset\nretrying\nready\nrequest\nrequest\nerror: timeout\n";
+
+    #[test]
+    fn test_search() {
+        let grep = LogGrep::new("^error", 0);
+
+        assert_eq!(
+            vec![
+                GrepLine {
+                    number: 3,
+                    line: "error: connection reset",
+                    stderr: false,
+                    matched: true,
+                },
+                GrepLine {
+                    number: 8,
+                    line: "error: timeout",
+                    stderr: false,
+                    matched: true,
+                },
+            ],
+            grep.search(LOGS, false)
+        );
+        assert!(LogGrep::new("panic", 2).search(LOGS, false).is_empty());
+    }
+
+    #[test]
+    fn test_format_context() {
+        let grep = LogGrep::new("^error", 1);
+        let lines = grep.search(LOGS, false);
+
+        assert_eq!(
+            vec![
+                "2-listening on :80",
+                "3:error: connection reset",
+                "4-retrying",
+                "--",
+                "7-request",
+                "8:error: timeout",
+            ],
+            grep.format(&lines, false)
+        );
+        assert_eq!(
+            vec!["1:\x1b[1;31merror\x1b[0m: timeout"],
+            grep.format(&grep.search("error: timeout", true), true)
+        );
+    }
+}
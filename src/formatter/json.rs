@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::utility::{CommandError, NodeOutput};
+
+/// The format results are printed in.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human readable output, `ps` and `images` are printed as a single table
+    #[default]
+    Table,
+    /// A single JSON array with one object per node
+    Json,
+    /// One JSON object per node per line
+    Jsonl,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as clap::ValueEnum>::from_str(s, true)
+    }
+}
+
+/// The result of a command on a single node in a form that can be serialized.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct NodeResult {
+    /// This is `None` for errors that are not tied to a node, like a container not being found
+    pub hostname: Option<String>,
+    /// The container the command ran against, for commands on a single container
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// This is `None` when the command never ran on the node
+    pub duration_ms: Option<u128>,
+
+    /// Parsed output of commands that print a table
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows: Option<Vec<Value>>,
+}
+
+impl NodeResult {
+    /// Converts a result from `run_command`. `parse` is used to turn the output into rows for
+    /// commands that print a table, see `summary_values`.
+    pub fn from_result(
+        result: &Result<NodeOutput, CommandError>,
+        parse: Option<fn(&str) -> Vec<Value>>,
+    ) -> Self {
+        match result {
+            Ok(output) => Self {
+                hostname: Some(output.node.clone()),
+                container: output.container.clone(),
+                success: output.success(),
+                exit_code: output.exit_code,
+                stdout: output.stdout.clone(),
+                stderr: output.stderr.clone(),
+                duration_ms: Some(output.duration.as_millis()),
+                rows: parse.map(|parse| parse(&output.stdout)),
+            },
+            Err(e) => Self {
+                hostname: e.hostname().map(String::from),
+                container: None,
+                success: false,
+                exit_code: None,
+                stdout: String::new(),
+                stderr: e.to_string(),
+                duration_ms: None,
+                rows: None,
+            },
+        }
+    }
+}
+
+/// Parses the JSON array `docker inspect` prints, this is empty when a `--format` was used.
+pub fn inspect_values(output: &str) -> Vec<Value> {
+    serde_json::from_str(output).unwrap_or_default()
+}
+
+/// Merges the output of `docker inspect` on several nodes into a single array, taking
+/// `(hostname, output)` pairs. Every object gets the `hostname` of the node it was found on.
+pub fn merge_inspect(outputs: &[(&str, &str)]) -> Vec<Value> {
+    outputs
+        .iter()
+        .flat_map(|(hostname, output)| {
+            inspect_values(output)
+                .into_iter()
+                .map(move |value| match value {
+                    Value::Object(object) => {
+                        let mut annotated = Map::new();
+                        annotated.insert("hostname".into(), Value::from(*hostname));
+                        annotated.extend(object);
+
+                        Value::Object(annotated)
+                    }
+                    value => value,
+                })
+        })
+        .collect()
+}
+
+/// Prints the results as JSON, either as a single array or as one object per line.
+pub fn print_json(results: &[NodeResult], format: OutputFormat) {
+    match format {
+        OutputFormat::Jsonl => {
+            for result in results {
+                // serializing a struct of strings and maps can not fail
+                println!("{}", serde_json::to_string(result).unwrap());
+            }
+        }
+        _ => println!("{}", serde_json::to_string_pretty(results).unwrap()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use std::time::Duration;
+
+    use super::{merge_inspect, NodeResult};
+    use crate::formatter::{summary_values, ImageSummary};
+    use crate::utility::{CommandError, NodeOutput};
+
+    #[test]
+    fn test_node_result_rows() {
+        let image = r#"{"Containers":"N/A","CreatedAt":"2023-02-14 12:00:00 +0000 UTC","CreatedSince":"2 weeks ago","Digest":"<none>","ID":"3f8a00f137a0","Repository":"nginx","SharedSize":"N/A","Size":"142MB","Tag":"latest","UniqueSize":"N/A","VirtualSize":"142MB"}"#;
+        let result: Result<NodeOutput, CommandError> = Ok(NodeOutput {
+            node: "abc".into(),
+            container: None,
+            exit_code: Some(0),
+            stdout: format!("{}\n", image),
+            stderr: String::new(),
+            duration: Duration::from_millis(1500),
+        });
+
+        let node_result = NodeResult::from_result(&result, Some(summary_values::<ImageSummary>));
+
+        assert_eq!(
+            json!({
+                "hostname": "abc",
+                "success": true,
+                "exit_code": 0,
+                "stdout": format!("{}\n", image),
+                "stderr": "",
+                "duration_ms": 1500,
+                "rows": [{
+                    "ID": "3f8a00f137a0",
+                    "Repository": "nginx",
+                    "Tag": "latest",
+                    "Digest": "<none>",
+                    "CreatedAt": "2023-02-14 12:00:00 +0000 UTC",
+                    "CreatedSince": "2 weeks ago",
+                    "Size": "142MB",
+                }],
+            }),
+            serde_json::to_value(node_result).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_node_result_error() {
+        let result: Result<NodeOutput, CommandError> = Err(CommandError::NoNodesFound("web"));
+
+        let node_result = NodeResult::from_result(&result, None);
+
+        assert_eq!(
+            json!({
+                "hostname": null,
+                "success": false,
+                "exit_code": null,
+                "stdout": "",
+                "stderr": "No node found containing the following container: web",
+                "duration_ms": null,
+            }),
+            serde_json::to_value(node_result).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_merge_inspect() {
+        let abc = r#"[{"Id": "3f8a00f137a0", "Name": "/web"}]"#;
+        let def = r#"[{"Id": "a1b2c3d4e5f6", "Name": "/web"}]"#;
+
+        assert_eq!(
+            vec![
+                json!({"hostname": "abc", "Id": "3f8a00f137a0", "Name": "/web"}),
+                json!({"hostname": "def", "Id": "a1b2c3d4e5f6", "Name": "/web"}),
+            ],
+            merge_inspect(&[("abc", abc), ("def", def), ("ghi", "'/web'\n")])
+        );
+        assert_eq!(
+            vec!["hostname", "Id", "Name"],
+            merge_inspect(&[("abc", abc)])[0]
+                .as_object()
+                .unwrap()
+                .keys()
+                .collect::<Vec<&String>>()
+        );
+    }
+}
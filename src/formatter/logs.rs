@@ -0,0 +1,216 @@
+use std::time::Duration;
+
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use crate::utility::NodeOutput;
+
+/// Colors the prefixes cycle through, the same ones docker compose uses.
+const COLORS: [&str; 6] = ["36", "33", "32", "35", "34", "31"];
+
+/// How long followed lines are held back so lines from different containers can be put in
+/// timestamp order.
+const SORT_WINDOW: Duration = Duration::from_millis(200);
+
+/// A single line of log output of one of the containers a `LogPrinter` prints.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogLine {
+    /// Index of the container in the sources of the printer
+    pub source: usize,
+    pub line: String,
+    pub stderr: bool,
+}
+
+impl LogLine {
+    /// With `--timestamps` docker starts every line with an RFC 3339 timestamp that has a fixed
+    /// number of digits, so these sort correctly as strings.
+    fn timestamp(&self) -> &str {
+        self.line.split(' ').next().unwrap_or_default()
+    }
+}
+
+/// Sends the lines of a single followed container to the printer.
+#[derive(Clone, Debug)]
+pub struct LogSink {
+    pub tx: UnboundedSender<LogLine>,
+    pub source: usize,
+}
+
+impl LogSink {
+    pub fn send(&self, line: String, stderr: bool) {
+        // the printer only goes away once every sink is dropped
+        let _ = self.tx.send(LogLine {
+            source: self.source,
+            line,
+            stderr,
+        });
+    }
+}
+
+/// Prints the logs of several containers interleaved, every line is prefixed with
+/// `host/container |` like docker compose does. The logs of a single container are printed as
+/// docker prints them.
+#[derive(Debug)]
+pub struct LogPrinter {
+    prefixes: Vec<String>,
+    sort: bool,
+}
+
+impl LogPrinter {
+    /// `sources` are the `(host, container)` pairs the lines come from. Lines are sorted by
+    /// timestamp when `sort` is set, which only works when docker was asked for timestamps.
+    pub fn new(sources: &[(&str, &str)], color: bool, sort: bool) -> Self {
+        if sources.len() == 1 {
+            return Self {
+                prefixes: vec![String::new()],
+                sort,
+            };
+        }
+
+        let names: Vec<String> = sources
+            .iter()
+            .map(|(host, container)| format!("{}/{}", host, container))
+            .collect();
+        let width = names.iter().map(String::len).max().unwrap_or_default();
+
+        let prefixes = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| match color {
+                true => format!(
+                    "\x1b[{}m{:width$} |\x1b[0m ",
+                    COLORS[index % COLORS.len()],
+                    name,
+                    width = width
+                ),
+                false => format!("{:width$} | ", name, width = width),
+            })
+            .collect();
+
+        Self { prefixes, sort }
+    }
+
+    pub fn format(&self, line: &LogLine) -> String {
+        format!("{}{}", self.prefixes[line.source], line.line)
+    }
+
+    fn print(&self, line: &LogLine) {
+        match line.stderr {
+            true => eprintln!("{}", self.format(line)),
+            false => println!("{}", self.format(line)),
+        }
+    }
+
+    /// Prints lines that were all collected up front.
+    pub fn print_all(&self, mut lines: Vec<LogLine>) {
+        if self.sort {
+            lines.sort_by(|a, b| a.timestamp().cmp(b.timestamp()));
+        }
+
+        for line in &lines {
+            self.print(line);
+        }
+    }
+
+    /// Prints lines as they arrive until every sink is dropped. When sorting, lines are held
+    /// back for a short moment so lines that arrive close together come out in order.
+    pub async fn print_stream(&self, mut rx: UnboundedReceiver<LogLine>) {
+        if !self.sort {
+            while let Some(line) = rx.recv().await {
+                self.print(&line);
+            }
+            return;
+        }
+
+        let mut buffer: Vec<LogLine> = vec![];
+        let mut interval = tokio::time::interval(SORT_WINDOW);
+
+        loop {
+            tokio::select! {
+                line = rx.recv() => match line {
+                    Some(line) => buffer.push(line),
+                    None => break,
+                },
+                _ = interval.tick() => self.print_all(std::mem::take(&mut buffer)),
+            }
+        }
+
+        self.print_all(buffer);
+    }
+}
+
+/// Prints the captured logs of several containers, `outputs` need to have their container set.
+pub fn print_logs(outputs: &[&NodeOutput], color: bool, sort: bool) {
+    let sources: Vec<(&str, &str)> = outputs
+        .iter()
+        .map(|output| {
+            (
+                output.node.as_str(),
+                output.container.as_deref().unwrap_or_default(),
+            )
+        })
+        .collect();
+    let printer = LogPrinter::new(&sources, color, sort);
+
+    let mut lines: Vec<LogLine> = vec![];
+    for (source, output) in outputs.iter().enumerate() {
+        for (text, stderr) in [(&output.stdout, false), (&output.stderr, true)] {
+            lines.extend(text.lines().map(|line| LogLine {
+                source,
+                line: line.to_string(),
+                stderr,
+            }));
+        }
+    }
+
+    printer.print_all(lines);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LogLine, LogPrinter};
+
+    fn line(source: usize, line: &str) -> LogLine {
+        LogLine {
+            source,
+            line: line.into(),
+            stderr: false,
+        }
+    }
+
+    #[test]
+    fn test_prefixes() {
+        let printer = LogPrinter::new(&[("abc", "api"), ("server1", "api")], false, false);
+
+        assert_eq!("abc/api     | started", printer.format(&line(0, "started")));
+        assert_eq!("server1/api | started", printer.format(&line(1, "started")));
+
+        let printer = LogPrinter::new(&[("abc", "api"), ("abc", "db")], true, false);
+
+        assert_eq!(
+            "\x1b[36mabc/api |\x1b[0m started",
+            printer.format(&line(0, "started"))
+        );
+
+        let printer = LogPrinter::new(&[("abc", "api")], true, false);
+
+        assert_eq!("started", printer.format(&line(0, "started")));
+    }
+
+    #[test]
+    fn test_timestamp_order() {
+        let mut lines = [
+            line(0, "2023-03-01T10:00:02.000000000Z second"),
+            line(1, "2023-03-01T10:00:01.000000000Z first"),
+            line(1, "2023-03-01T10:00:03.000000000Z third"),
+        ];
+        lines.sort_by(|a, b| a.timestamp().cmp(b.timestamp()));
+
+        assert_eq!(
+            vec!["first", "second", "third"],
+            lines
+                .iter()
+                .map(|line| line.line.split(' ').nth(1).unwrap())
+                .collect::<Vec<&str>>()
+        );
+    }
+}
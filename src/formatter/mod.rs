@@ -1,5 +1,15 @@
 //! This module contains all the logic regarding any output to the terminal
 
+mod grep;
+mod json;
+mod logs;
+mod picker;
 mod stdout;
+mod summary;
 
+pub use grep::*;
+pub use json::*;
+pub use logs::*;
+pub use picker::*;
 pub use stdout::*;
+pub use summary::*;
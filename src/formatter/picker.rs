@@ -0,0 +1,89 @@
+use dialoguer::FuzzySelect;
+
+use super::ContainerSummary;
+
+/// Docker shortens container ids to this many characters in its own tables.
+const SHORT_ID: usize = 12;
+
+/// Formats the candidates as aligned `host, container id, name, image, status` rows.
+pub fn candidate_rows(candidates: &[(&str, &ContainerSummary)]) -> Vec<String> {
+    let columns: Vec<[&str; 5]> = candidates
+        .iter()
+        .map(|(node, container)| {
+            [
+                *node,
+                &container.id[..container.id.len().min(SHORT_ID)],
+                container.name(),
+                &container.image,
+                &container.status,
+            ]
+        })
+        .collect();
+
+    let mut widths = [0; 5];
+    for row in &columns {
+        for (width, item) in widths.iter_mut().zip(row) {
+            *width = (*width).max(item.len());
+        }
+    }
+
+    columns
+        .iter()
+        .map(|row| {
+            row.iter()
+                .zip(widths)
+                .map(|(item, width)| format!("{:width$}", item, width = width))
+                .collect::<Vec<String>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Lets the user pick one of the candidates with fuzzy filtering and returns its index. This
+/// returns `None` when there is nothing to pick from or the picker was cancelled.
+pub fn pick_container(prompt: &str, candidates: &[(&str, &ContainerSummary)]) -> Option<usize> {
+    if candidates.is_empty() {
+        return None;
+    }
+
+    FuzzySelect::new()
+        .with_prompt(prompt)
+        .items(&candidate_rows(candidates))
+        .default(0)
+        .interact_opt()
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{candidate_rows, ContainerSummary};
+
+    #[test]
+    fn test_candidate_rows() {
+        let web = ContainerSummary {
+            id: "3f8a00f137a0d1c5e9a1".into(),
+            names: "web".into(),
+            image: "nginx".into(),
+            status: "Up 2 weeks".into(),
+            ..Default::default()
+        };
+        let worker = ContainerSummary {
+            id: "a1b2c3d4e5f6".into(),
+            names: "web-worker,other".into(),
+            image: "busybox".into(),
+            status: "Exited (0) 1 day ago".into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec![
+                "abc      3f8a00f137a0  web         nginx    Up 2 weeks",
+                "server1  a1b2c3d4e5f6  web-worker  busybox  Exited (0) 1 day ago",
+            ],
+            candidate_rows(&[("abc", &web), ("server1", &worker)])
+        );
+    }
+}
@@ -1,7 +1,30 @@
 use defaultdict::DefaultHashMap;
 
+use super::summary::{
+    format_size, parse_json_lines, ContainerSummary, ImageSummary, StatsSummary, TopSummary,
+};
+use crate::utility::NodeOutput;
+
 const HOSTNAME: &str = "HOSTNAME";
 const OFFSET: usize = 2;
+/// Name of the rows that sum up the rows of a node or of all nodes.
+const TOTAL: &str = "TOTAL";
+
+/// What the rows of the stats table are sorted by, highest first.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsSort {
+    Cpu,
+    Mem,
+}
+
+impl StatsSort {
+    fn key(&self, stats: &StatsSummary) -> f64 {
+        match self {
+            Self::Cpu => stats.cpu(),
+            Self::Mem => stats.memory(),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Parser {
@@ -9,114 +32,248 @@ pub struct Parser {
     header_spacing: DefaultHashMap<String, usize>,
 
     internal: DefaultHashMap<String, Vec<Vec<String>>>,
+    /// The hosts in the order their first row was added in
+    hosts: Vec<String>,
 }
 
 impl Parser {
-    pub fn from_images_results(log: &str) -> Self {
-        let mut internal: DefaultHashMap<String, Vec<Vec<String>>> = DefaultHashMap::new();
+    /// Creates a table with the given headers, a hostname column is added in front of them.
+    fn new(headers: Vec<&str>) -> Self {
+        let mut headers: Vec<String> = headers.into_iter().map(String::from).collect();
         let mut header_spacing: DefaultHashMap<String, usize> = DefaultHashMap::new();
-        let mut headers: Vec<String> = vec![];
-        let mut node: Option<String> = None;
-
-        // The arms in this if block only execute the same action, they do check different things.
-        #[allow(clippy::if_same_then_else)]
-        for line in log.split('\n') {
-            if line.contains("REPOSITORY") {
-                let headers_iter: Vec<String> = line
-                    .split("  ")
-                    .filter(|item| !item.is_empty())
-                    .map(|item| String::from(item.trim()))
-                    .collect();
-                if headers.len() < headers_iter.len() {
-                    headers = headers_iter;
-                }
-                continue;
-            } else if line.contains("not found") {
-                continue;
-            } else if line.is_empty() {
-                continue;
-            } else if line.contains("Error") {
-                continue;
-            } else if !line.contains(' ') {
-                node = Some(String::from(line))
-            } else if let Some(node) = &node {
-                let mut placeholder: Vec<String> = vec![];
-                for item in line.split("  ").filter(|item| !item.is_empty()) {
-                    placeholder.push(String::from(item.trim()));
-                }
-                internal.get_mut(node).push(placeholder);
-            }
-        }
 
         headers.insert(0, HOSTNAME.to_string());
-        header_spacing.insert(String::from(HOSTNAME), String::from(HOSTNAME).len());
-
-        create_spacing(&headers, &mut header_spacing, &mut internal);
+        for header in &headers {
+            header_spacing.insert(header.clone(), header.len());
+        }
 
         Self {
             headers,
             header_spacing,
-            internal,
+            internal: DefaultHashMap::new(),
+            hosts: vec![],
         }
     }
 
-    pub fn from_ps_results(log: &str) -> Self {
-        let re = regex::Regex::new(r"/").unwrap();
+    fn push(&mut self, hostname: &str, row: Vec<String>) {
+        if !self.hosts.iter().any(|host| host == hostname) {
+            self.hosts.push(hostname.to_string());
+        }
 
-        let mut internal: DefaultHashMap<String, Vec<Vec<String>>> = DefaultHashMap::new();
-        let mut header_spacing: DefaultHashMap<String, usize> = DefaultHashMap::new();
-        let mut headers: Vec<String> = vec![];
-        let mut node: Option<String> = None;
-
-        // The arms in this if block only execute the same action, they do check different things.
-        #[allow(clippy::if_same_then_else)]
-        for line in log.split('\n') {
-            if line.contains("CONTAINER") {
-                let headers_iter: Vec<String> = line
-                    .split("  ")
-                    .filter(|item| !item.is_empty())
-                    .map(|item| String::from(item.trim()))
-                    .collect();
-                if headers.len() < headers_iter.len() {
-                    headers = headers_iter;
+        self.internal.get_mut(&hostname.to_string()).push(row);
+    }
+
+    /// Builds the images table from `(hostname, output)` pairs, where the output was formatted
+    /// with `JSON_FORMAT`.
+    pub fn from_images_results(results: &[(&str, &str)], digests: bool) -> Self {
+        let mut headers = vec!["REPOSITORY", "TAG"];
+        if digests {
+            headers.push("DIGEST");
+        }
+        headers.extend(["IMAGE ID", "CREATED", "SIZE"]);
+
+        let mut parser = Self::new(headers);
+
+        for (hostname, output) in results {
+            for image in parse_json_lines::<ImageSummary>(output) {
+                let mut row = vec![image.repository, image.tag];
+                if digests {
+                    row.push(image.digest);
                 }
-                continue;
-            } else if line.contains("not found") {
-                continue;
-            } else if line.is_empty() {
-                continue;
-            } else if line.contains("Error") {
-                continue;
-            } else if !line.contains(' ') {
-                node = Some(String::from(line))
-            } else if let Some(node) = &node {
-                let mut placeholder: Vec<String> = vec![];
-                for (index, item) in line.split("  ").filter(|item| !item.is_empty()).enumerate() {
-                    if let Some(header) = headers.get(index) {
-                        if header.contains("PORT") && re.is_match(item) {
-                            placeholder.push(String::from(item.trim()));
-                        } else if header.contains("PORT") && !re.is_match(item) {
-                            placeholder.push(String::new());
-                            placeholder.push(String::from(item.trim()));
-                        } else {
-                            placeholder.push(String::from(item.trim()));
-                        }
-                    }
+                row.extend([image.id, image.created_since, image.size]);
+
+                parser.push(hostname, row);
+            }
+        }
+
+        create_spacing(
+            &parser.headers,
+            &mut parser.header_spacing,
+            &mut parser.internal,
+        );
+
+        parser
+    }
+
+    /// Builds the containers table from `(hostname, output)` pairs, where the output was
+    /// formatted with `JSON_FORMAT`.
+    pub fn from_ps_results(results: &[(&str, &str)], size: bool) -> Self {
+        let mut headers = vec![
+            "CONTAINER ID",
+            "IMAGE",
+            "COMMAND",
+            "CREATED",
+            "STATUS",
+            "PORTS",
+            "NAMES",
+        ];
+        if size {
+            headers.push("SIZE");
+        }
+
+        let mut parser = Self::new(headers);
+
+        for (hostname, output) in results {
+            for container in parse_json_lines::<ContainerSummary>(output) {
+                let mut row = vec![
+                    container.id,
+                    container.image,
+                    container.command,
+                    container.running_for,
+                    container.status,
+                    container.ports,
+                    container.names,
+                ];
+                if size {
+                    row.push(container.size);
                 }
-                internal.get_mut(node).push(placeholder);
+
+                parser.push(hostname, row);
             }
         }
 
-        headers.insert(0, HOSTNAME.to_string());
-        header_spacing.insert(String::from(HOSTNAME), String::from(HOSTNAME).len());
+        create_spacing(
+            &parser.headers,
+            &mut parser.header_spacing,
+            &mut parser.internal,
+        );
 
-        create_spacing(&headers, &mut header_spacing, &mut internal);
+        parser
+    }
 
-        Self {
-            headers,
-            header_spacing,
-            internal,
+    /// Builds the summary table of a command that ran on several nodes or in several containers.
+    /// The container column is left out when none of the outputs have a container.
+    pub fn from_exec_results(outputs: &[&NodeOutput]) -> Self {
+        let containers = outputs.iter().any(|output| output.container.is_some());
+        let mut headers = vec!["EXIT CODE", "DURATION"];
+        if containers {
+            headers.insert(0, "CONTAINER");
         }
+
+        let mut parser = Self::new(headers);
+
+        for output in outputs {
+            let mut row = vec![
+                // the remote process was killed by a signal when there is no exit code
+                output
+                    .exit_code
+                    .map_or("-".to_string(), |code| code.to_string()),
+                format!("{:.1}s", output.duration.as_secs_f64()),
+            ];
+            if containers {
+                row.insert(0, output.container.clone().unwrap_or_default());
+            }
+
+            parser.push(&output.node, row);
+        }
+
+        create_spacing(
+            &parser.headers,
+            &mut parser.header_spacing,
+            &mut parser.internal,
+        );
+
+        parser
+    }
+
+    /// Builds the table of the processes of several containers from the output of `docker top`,
+    /// `outputs` need to have their container set. The headers are the ones docker printed,
+    /// which depend on the ps options.
+    pub fn from_top_results(outputs: &[&NodeOutput]) -> Self {
+        let tops: Vec<(&NodeOutput, TopSummary)> = outputs
+            .iter()
+            .map(|output| (*output, TopSummary::parse(&output.stdout)))
+            .collect();
+
+        let mut headers = vec!["CONTAINER"];
+        if let Some((_, top)) = tops.iter().find(|(_, top)| !top.headers.is_empty()) {
+            headers.extend(top.headers.iter().map(String::as_str));
+        }
+
+        let mut parser = Self::new(headers);
+
+        for (output, top) in &tops {
+            for process in &top.processes {
+                let mut row = vec![output.container.clone().unwrap_or_default()];
+                row.extend(process.iter().cloned());
+
+                parser.push(&output.node, row);
+            }
+        }
+
+        create_spacing(
+            &parser.headers,
+            &mut parser.header_spacing,
+            &mut parser.internal,
+        );
+
+        parser
+    }
+
+    /// Builds the stats table from `(hostname, output)` pairs, where the output was formatted
+    /// with `JSON_FORMAT`. Every node ends with a row that sums up its containers and the table
+    /// ends with a row for all nodes. With `sort` the containers, and the nodes by their totals,
+    /// are sorted highest first.
+    pub fn from_stats_results(results: &[(&str, &str)], sort: Option<StatsSort>) -> Self {
+        let mut parser = Self::new(vec![
+            "CONTAINER ID",
+            "NAME",
+            "CPU %",
+            "MEM USAGE / LIMIT",
+            "MEM %",
+            "NET I/O",
+            "BLOCK I/O",
+            "PIDS",
+        ]);
+
+        let mut nodes: Vec<(&str, Vec<StatsSummary>)> = results
+            .iter()
+            .map(|(hostname, output)| (*hostname, parse_json_lines::<StatsSummary>(output)))
+            .collect();
+
+        if let Some(sort) = sort {
+            let total = |stats: &[StatsSummary]| stats.iter().map(|s| sort.key(s)).sum::<f64>();
+
+            for (_, stats) in &mut nodes {
+                stats.sort_by(|a, b| sort.key(b).total_cmp(&sort.key(a)));
+            }
+            nodes.sort_by(|(_, a), (_, b)| total(b).total_cmp(&total(a)));
+        }
+
+        for (hostname, stats) in &nodes {
+            for container in stats {
+                parser.push(
+                    hostname,
+                    vec![
+                        container.id.clone(),
+                        container.name.clone(),
+                        container.cpu_perc.clone(),
+                        container.mem_usage.clone(),
+                        container.mem_perc.clone(),
+                        container.net_io.clone(),
+                        container.block_io.clone(),
+                        container.pids.clone(),
+                    ],
+                );
+            }
+
+            // the memory percentages are left out, they are relative to the limit of each
+            // container and do not add up
+            parser.push(hostname, total_row(stats.iter()));
+        }
+
+        if !nodes.is_empty() {
+            let all = nodes.iter().flat_map(|(_, stats)| stats.iter());
+            parser.push(TOTAL, total_row(all));
+        }
+
+        create_spacing(
+            &parser.headers,
+            &mut parser.header_spacing,
+            &mut parser.internal,
+        );
+
+        parser
     }
 
     pub fn print(&mut self) {
@@ -135,7 +292,7 @@ impl Parser {
             headers.push('\t');
         }
 
-        for host in self.internal.keys() {
+        for host in &self.hosts {
             let lines = self.internal.get(host);
             for line in lines {
                 let host_spacing: usize = *self.header_spacing.get(HOSTNAME);
@@ -170,6 +327,24 @@ impl Parser {
     }
 }
 
+/// Sums up the CPU usage, memory usage and number of processes of `stats`.
+fn total_row<'s>(stats: impl Iterator<Item = &'s StatsSummary> + Clone) -> Vec<String> {
+    let cpu: f64 = stats.clone().map(StatsSummary::cpu).sum();
+    let memory: f64 = stats.clone().map(StatsSummary::memory).sum();
+    let pids: u64 = stats.map(StatsSummary::pids).sum();
+
+    vec![
+        String::new(),
+        TOTAL.to_string(),
+        format!("{:.2}%", cpu),
+        format_size(memory),
+        String::new(),
+        String::new(),
+        String::new(),
+        pids.to_string(),
+    ]
+}
+
 fn create_spacing(
     headers: &[String],
     header_spacing: &mut DefaultHashMap<String, usize>,
@@ -189,3 +364,90 @@ fn create_spacing(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{NodeOutput, Parser, StatsSort};
+
+    #[test]
+    fn test_from_exec_results() {
+        let output = NodeOutput {
+            node: "node1".into(),
+            container: Some("app".into()),
+            exit_code: Some(1),
+            stdout: String::new(),
+            stderr: String::new(),
+            duration: Duration::from_millis(1300),
+        };
+
+        let parser = Parser::from_exec_results(&[&output]);
+
+        assert_eq!(
+            vec![vec!["app", "1", "1.3s"]],
+            *parser.internal.get("node1")
+        );
+        assert_eq!("EXIT CODE", parser.headers[2]);
+
+        let output = NodeOutput {
+            container: None,
+            ..output
+        };
+        let parser = Parser::from_exec_results(&[&output]);
+
+        assert_eq!(vec![vec!["1", "1.3s"]], *parser.internal.get("node1"));
+    }
+
+    #[test]
+    fn test_from_ps_results() {
+        let output = r#"{"Command":"\"sleep infinity\"","CreatedAt":"2023-03-01 10:00:00 +0000 UTC","ID":"a1b2c3d4e5f6","Image":"busybox","Labels":"","Names":"sleepy  container","Ports":"","RunningFor":"2 weeks ago","Status":"Error  Exited (1) 1 day ago"}
+"#;
+
+        let parser = Parser::from_ps_results(&[("node1", output), ("node2", "")], false);
+
+        assert_eq!(
+            vec![vec![
+                "a1b2c3d4e5f6",
+                "busybox",
+                "\"sleep infinity\"",
+                "2 weeks ago",
+                "Error  Exited (1) 1 day ago",
+                "",
+                "sleepy  container",
+            ]],
+            *parser.internal.get("node1")
+        );
+        assert!(parser.internal.get("node2").is_empty());
+        assert_eq!("CONTAINER ID", parser.headers[1]);
+    }
+
+    #[test]
+    fn test_from_stats_results() {
+        let node1 = r#"{"BlockIO":"0B / 0B","CPUPerc":"1.00%","ID":"a1b2c3d4e5f6","MemPerc":"1.00%","MemUsage":"1MiB / 1GiB","Name":"idle","NetIO":"0B / 0B","PIDs":"1"}
+{"BlockIO":"0B / 0B","CPUPerc":"50.00%","ID":"3f8a00f137a0","MemPerc":"2.00%","MemUsage":"3MiB / 1GiB","Name":"busy","NetIO":"0B / 0B","PIDs":"4"}"#;
+        let node2 = r#"{"BlockIO":"0B / 0B","CPUPerc":"10.00%","ID":"0a1b2c3d4e5f","MemPerc":"1.00%","MemUsage":"1MiB / 1GiB","Name":"web","NetIO":"0B / 0B","PIDs":"2"}"#;
+
+        let parser =
+            Parser::from_stats_results(&[("node2", node2), ("node1", node1)], Some(StatsSort::Cpu));
+
+        assert_eq!(vec!["node1", "node2", "TOTAL"], parser.hosts);
+        assert_eq!(
+            vec!["busy", "idle", "TOTAL"],
+            parser
+                .internal
+                .get("node1")
+                .iter()
+                .map(|row| row[1].as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert_eq!(
+            vec!["", "TOTAL", "51.00%", "4.00MiB", "", "", "", "5"],
+            parser.internal.get("node1")[2]
+        );
+        assert_eq!(
+            vec!["", "TOTAL", "61.00%", "5.00MiB", "", "", "", "7"],
+            parser.internal.get("TOTAL")[0]
+        );
+    }
+}
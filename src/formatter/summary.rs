@@ -0,0 +1,346 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Go template that makes docker print one JSON object per line.
+pub const JSON_FORMAT: &str = "{{json .}}";
+
+/// A container as printed by `docker ps --format '{{json .}}'`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerSummary {
+    #[serde(rename = "ID")]
+    pub id: String,
+    pub image: String,
+    pub command: String,
+    pub created_at: String,
+    pub running_for: String,
+    pub status: String,
+    /// Only reported by docker 20.10 and newer
+    #[serde(default)]
+    pub state: String,
+    pub ports: String,
+    pub names: String,
+    /// Only filled in when `--size` is passed
+    #[serde(default)]
+    pub size: String,
+    #[serde(default)]
+    pub labels: String,
+}
+
+impl ContainerSummary {
+    /// Returns the first name of the container, containers only have more than one when they
+    /// are linked.
+    pub fn name(&self) -> &str {
+        self.names.split(',').next().unwrap_or_default()
+    }
+}
+
+/// An image as printed by `docker images --format '{{json .}}'`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ImageSummary {
+    #[serde(rename = "ID")]
+    pub id: String,
+    pub repository: String,
+    pub tag: String,
+    pub digest: String,
+    pub created_at: String,
+    pub created_since: String,
+    pub size: String,
+}
+
+/// Parses output of docker that was formatted with `JSON_FORMAT`. Lines that are not a valid
+/// object, such as warnings docker prints, are skipped.
+pub fn parse_json_lines<T: DeserializeOwned>(output: &str) -> Vec<T> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Parses output like `parse_json_lines` and returns the typed summaries as JSON values, which
+/// drops any fields docker prints that are not part of `T`.
+pub fn summary_values<T: DeserializeOwned + Serialize>(output: &str) -> Vec<Value> {
+    parse_json_lines::<T>(output)
+        .iter()
+        // serializing a struct of strings can not fail
+        .map(|summary| serde_json::to_value(summary).unwrap())
+        .collect()
+}
+
+/// Resource usage of a container as printed by `docker stats --format '{{json .}}'`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct StatsSummary {
+    #[serde(rename = "ID")]
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "CPUPerc")]
+    pub cpu_perc: String,
+    pub mem_usage: String,
+    pub mem_perc: String,
+    #[serde(rename = "NetIO")]
+    pub net_io: String,
+    #[serde(rename = "BlockIO")]
+    pub block_io: String,
+    #[serde(rename = "PIDs")]
+    pub pids: String,
+}
+
+impl StatsSummary {
+    /// CPU usage in percent, this goes over 100 for containers that use more than one core.
+    pub fn cpu(&self) -> f64 {
+        parse_percentage(&self.cpu_perc)
+    }
+
+    /// Memory usage in bytes, without the limit docker prints after it.
+    pub fn memory(&self) -> f64 {
+        parse_size(self.mem_usage.split('/').next().unwrap_or_default())
+    }
+
+    pub fn pids(&self) -> u64 {
+        self.pids.parse().unwrap_or_default()
+    }
+}
+
+/// The parts of `docker info --format '{{json .}}'` that tell how busy a node is and how much it
+/// can take.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InfoSummary {
+    pub containers_running: usize,
+    #[serde(rename = "NCPU")]
+    pub ncpu: usize,
+    pub mem_total: u64,
+}
+
+/// The processes of a container as printed by `docker top`, a table with a header line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TopSummary {
+    pub headers: Vec<String>,
+    pub processes: Vec<Vec<String>>,
+}
+
+impl TopSummary {
+    /// Splits the columns on whitespace. Only the last column, the command, can contain spaces
+    /// so everything after the second to last column belongs to it.
+    pub fn parse(output: &str) -> Self {
+        let mut lines = output.lines().filter(|line| !line.trim().is_empty());
+        let headers: Vec<String> = match lines.next() {
+            Some(line) => line.split_whitespace().map(String::from).collect(),
+            None => return Self::default(),
+        };
+
+        let processes = lines
+            .map(|line| {
+                let mut columns: Vec<String> = vec![];
+                let mut rest = line.trim_start();
+
+                while columns.len() + 1 < headers.len() {
+                    let (column, remainder) =
+                        rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                    columns.push(column.to_string());
+                    rest = remainder.trim_start();
+                }
+                columns.push(rest.trim_end().to_string());
+
+                columns
+            })
+            .collect();
+
+        Self { headers, processes }
+    }
+}
+
+/// Parses the output of `docker top` into one object per process, keyed by the headers. Every
+/// object also gets the `container` the process runs in.
+pub fn top_values(container: &str, output: &str) -> Vec<Value> {
+    let top = TopSummary::parse(output);
+
+    top.processes
+        .iter()
+        .map(|process| {
+            let mut object = Map::new();
+            object.insert("container".into(), Value::from(container));
+            object.extend(
+                top.headers
+                    .iter()
+                    .cloned()
+                    .zip(process.iter().cloned().map(Value::from)),
+            );
+
+            Value::Object(object)
+        })
+        .collect()
+}
+
+/// Units docker prints sizes in, with their size in bytes.
+const UNITS: [(&str, f64); 9] = [
+    ("TiB", 1099511627776.0),
+    ("GiB", 1073741824.0),
+    ("MiB", 1048576.0),
+    ("KiB", 1024.0),
+    ("TB", 1e12),
+    ("GB", 1e9),
+    ("MB", 1e6),
+    ("kB", 1e3),
+    ("B", 1.0),
+];
+
+/// Parses a percentage like `12.50%`, anything that is not a number counts as 0.
+pub fn parse_percentage(percentage: &str) -> f64 {
+    percentage
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .unwrap_or_default()
+}
+
+/// Parses a size like `1.5MiB` or `12kB` into bytes, anything that is not a size counts as 0.
+pub fn parse_size(size: &str) -> f64 {
+    let size = size.trim();
+
+    UNITS
+        .iter()
+        .find_map(|(unit, bytes)| {
+            size.strip_suffix(unit)
+                .and_then(|number| number.parse::<f64>().ok())
+                .map(|number| number * bytes)
+        })
+        .unwrap_or_default()
+}
+
+/// Formats bytes in the binary units docker uses for memory.
+pub fn format_size(bytes: f64) -> String {
+    match UNITS[..4].iter().find(|(_, size)| bytes >= *size) {
+        Some((unit, size)) => format!("{:.2}{}", bytes / size, unit),
+        None => format!("{}B", bytes),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        format_size, parse_json_lines, parse_size, top_values, ContainerSummary, ImageSummary,
+        StatsSummary, TopSummary,
+    };
+
+    #[test]
+    fn test_parse_container_summary() {
+        let output = r#"{"Command":"\"/docker-entrypoint.…\"","CreatedAt":"2023-03-01 10:00:00 +0000 UTC","ID":"3f8a00f137a0","Image":"nginx","Labels":"","LocalVolumes":"0","Mounts":"","Names":"web","Networks":"bridge","Ports":"","RunningFor":"2 weeks ago","Size":"0B","State":"running","Status":"Up 2 weeks"}
+WARNING: this line is not json
+{"Command":"\"sleep infinity\"","CreatedAt":"2023-03-01 10:00:00 +0000 UTC","ID":"a1b2c3d4e5f6","Image":"busybox","Labels":"","LocalVolumes":"0","Mounts":"","Names":"sleepy  container","Networks":"bridge","Ports":"0.0.0.0:80->80/tcp","RunningFor":"2 weeks ago","Status":"Exited (0) 1 day ago"}"#;
+
+        let correct: Vec<ContainerSummary> = vec![
+            ContainerSummary {
+                id: "3f8a00f137a0".into(),
+                image: "nginx".into(),
+                command: "\"/docker-entrypoint.…\"".into(),
+                created_at: "2023-03-01 10:00:00 +0000 UTC".into(),
+                running_for: "2 weeks ago".into(),
+                status: "Up 2 weeks".into(),
+                state: "running".into(),
+                ports: "".into(),
+                names: "web".into(),
+                size: "0B".into(),
+                labels: "".into(),
+            },
+            ContainerSummary {
+                id: "a1b2c3d4e5f6".into(),
+                image: "busybox".into(),
+                command: "\"sleep infinity\"".into(),
+                created_at: "2023-03-01 10:00:00 +0000 UTC".into(),
+                running_for: "2 weeks ago".into(),
+                status: "Exited (0) 1 day ago".into(),
+                state: "".into(),
+                ports: "0.0.0.0:80->80/tcp".into(),
+                names: "sleepy  container".into(),
+                size: "".into(),
+                labels: "".into(),
+            },
+        ];
+
+        assert_eq!(correct, parse_json_lines::<ContainerSummary>(output));
+    }
+
+    #[test]
+    fn test_parse_image_summary() {
+        let output = r#"{"Containers":"N/A","CreatedAt":"2023-02-14 12:00:00 +0000 UTC","CreatedSince":"2 weeks ago","Digest":"<none>","ID":"3f8a00f137a0","Repository":"nginx","SharedSize":"N/A","Size":"142MB","Tag":"latest","UniqueSize":"N/A","VirtualSize":"142MB"}"#;
+
+        let correct: Vec<ImageSummary> = vec![ImageSummary {
+            id: "3f8a00f137a0".into(),
+            repository: "nginx".into(),
+            tag: "latest".into(),
+            digest: "<none>".into(),
+            created_at: "2023-02-14 12:00:00 +0000 UTC".into(),
+            created_since: "2 weeks ago".into(),
+            size: "142MB".into(),
+        }];
+
+        assert_eq!(correct, parse_json_lines::<ImageSummary>(output));
+    }
+
+    #[test]
+    fn test_parse_stats_summary() {
+        let output = r#"{"BlockIO":"4.1MB / 0B","CPUPerc":"12.50%","Container":"3f8a00f137a0","ID":"3f8a00f137a0","MemPerc":"1.95%","MemUsage":"150.2MiB / 7.5GiB","Name":"web","NetIO":"1.2kB / 0B","PIDs":"5"}"#;
+
+        let stats = &parse_json_lines::<StatsSummary>(output)[0];
+
+        assert_eq!("web", stats.name);
+        assert_eq!(12.5, stats.cpu());
+        assert_eq!(150.2 * 1048576.0, stats.memory());
+        assert_eq!(5, stats.pids());
+    }
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(1536.0, parse_size("1.5KiB"));
+        assert_eq!(12000.0, parse_size(" 12kB"));
+        assert_eq!(0.0, parse_size("--"));
+        assert_eq!("1.50GiB", format_size(1610612736.0));
+        assert_eq!("512B", format_size(512.0));
+    }
+
+    #[test]
+    fn test_parse_top_summary() {
+        let output = "UID                 PID                 PPID                C                   STIME               TTY                 TIME                CMD
+root                1234                1210                0                   Mar01               ?                   00:00:01            nginx: master process nginx -g daemon off;
+101                 1290                1234                0                   Mar01               ?                   00:00:00            nginx: worker process
+";
+
+        let top = TopSummary::parse(output);
+
+        assert_eq!(8, top.headers.len());
+        assert_eq!(
+            vec![
+                "root",
+                "1234",
+                "1210",
+                "0",
+                "Mar01",
+                "?",
+                "00:00:01",
+                "nginx: master process nginx -g daemon off;"
+            ],
+            top.processes[0]
+        );
+        assert_eq!("nginx: worker process", top.processes[1][7]);
+        assert_eq!(TopSummary::default(), TopSummary::parse(""));
+    }
+
+    #[test]
+    fn test_top_values() {
+        let output = "UID                 PID                 CMD
+root                1234                nginx: master process
+";
+
+        let values = top_values("web", output);
+
+        assert_eq!(1, values.len());
+        assert_eq!("web", values[0]["container"]);
+        assert_eq!("1234", values[0]["PID"]);
+        assert_eq!("nginx: master process", values[0]["CMD"]);
+        assert!(top_values("web", "").is_empty());
+    }
+}
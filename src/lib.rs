@@ -1,73 +1,377 @@
 pub mod constants {
     pub const CONCURRENT_REQUESTS: usize = 10;
+    /// Seconds to wait for an ssh connection to be established
+    pub const CONNECT_TIMEOUT: u64 = 1;
+    /// Seconds `stats` waits at least between two refreshes of its table
+    pub const STATS_INTERVAL: u64 = 2;
 }
 
 mod cli;
 mod client;
+mod config;
 mod formatter;
 mod utility;
 
+use std::io::IsTerminal;
+
 use clap::{CommandFactory, Parser};
 use clap_complete::generate;
 
-pub async fn run() {
+/// Runs docker-manager and returns the exit code of the process, see `utility::ExitCode`.
+pub async fn run() -> i32 {
     let mut _cli = cli::App::parse();
+    config::apply(&mut _cli);
 
-    let regex: Option<&str> = match &_cli.regex {
-        Some(reg) => Some(reg),
-        None => None,
+    let selector = client::NodeSelector {
+        inventory: _cli.inventory.as_deref(),
+        regex: _cli.regex.as_deref(),
+        groups: &_cli.group,
+        tags: &_cli.tag,
     };
 
-    let identity_file: Option<&str> = match &_cli.identity_file {
-        Some(id) => Some(id),
-        None => None,
+    let output = _cli.output.unwrap_or_default();
+
+    let options = client::RunOptions {
+        sudo: match (_cli.sudo, _cli.no_sudo) {
+            (true, _) => Some(true),
+            (false, true) => Some(false),
+            (false, false) => None,
+        },
+        identity_file: _cli.identity_file.as_deref(),
+        // at least one node needs to be connected to at a time for anything to happen
+        concurrency: _cli
+            .concurrency
+            .unwrap_or(constants::CONCURRENT_REQUESTS)
+            .max(1),
+        connect_timeout: std::time::Duration::from_secs(
+            _cli.connect_timeout.unwrap_or(constants::CONNECT_TIMEOUT),
+        ),
+        timeout: _cli.timeout.map(std::time::Duration::from_secs),
+        match_mode: _cli.match_mode,
+        // the picker needs a terminal to read from and would end up in the JSON output otherwise
+        pick: output == formatter::OutputFormat::Table
+            && std::io::stdin().is_terminal()
+            && std::io::stdout().is_terminal(),
     };
 
-    let mut result: String = String::new();
-    let mut parse: bool = false;
-    let mut command: String = String::new();
+    let mode = OutputMode::new(&_cli.command);
+
+    if let cli::Command::Completion { shell } = _cli.command {
+        generate_completion(shell);
+        return 0;
+    }
+
+    // `stats` keeps refreshing its table, the JSON formats only print a single sweep
+    if let (
+        cli::Command::Stats {
+            container_id,
+            all,
+            no_trunc,
+            sort,
+            ..
+        },
+        false,
+    ) = (&_cli.command, stats_once(&_cli.command, output))
+    {
+        let container_id = container_id.iter().map(String::as_str).collect();
+        return watch_stats(container_id, *all, *no_trunc, &selector, &options, *sort).await;
+    }
+
+    let internal_command = _cli.command.internal_reference_command();
+    let streaming = internal_command.is_streaming();
+    let results = utility::run_command(internal_command, &selector, &options).await;
+    let code = utility::ExitCode::from_results(&results) as i32;
+
+    // The output of an attached exec or followed logs already went to the terminal, only the
+    // exit code is left
+    if streaming {
+        for result in results.iter().filter_map(|result| result.as_ref().err()) {
+            eprintln!("{}", result);
+        }
+
+        // nodes that could not be searched do not change the exit code of a single command
+        let outputs: Vec<&utility::NodeOutput> = results.iter().flatten().collect();
+        return match outputs.as_slice() {
+            [output] => output.exit_code.unwrap_or(code),
+            _ => code,
+        };
+    }
+
+    if let OutputMode::Export(dir) = &mode {
+        return match utility::export_logs(dir, &results) {
+            Ok(entries) => {
+                let failed: Vec<&utility::ExportEntry> =
+                    entries.iter().filter(|entry| entry.failed()).collect();
+                println!(
+                    "Exported the logs of {} containers to {}, see {}",
+                    entries.len() - failed.len(),
+                    dir.display(),
+                    dir.join(utility::MANIFEST).display()
+                );
+                for entry in failed {
+                    eprintln!(
+                        "{}/{}: {}",
+                        entry.hostname.as_deref().unwrap_or("-"),
+                        entry.container.as_deref().unwrap_or("-"),
+                        entry.error.as_deref().unwrap_or_default()
+                    );
+                }
+                code
+            }
+            Err(e) => {
+                eprintln!("Could not export the logs to {}\n{}", dir.display(), e);
+                utility::ExitCode::Failure as i32
+            }
+        };
+    }
+
+    if output != formatter::OutputFormat::Table {
+        let node_results: Vec<formatter::NodeResult> = results
+            .iter()
+            .map(|result| mode.node_result(result))
+            .collect();
+        formatter::print_json(&node_results, output);
+
+        return code;
+    }
+
+    mode.print(&results);
+
+    code
+}
 
-    match _cli.command {
-        cli::Command::Completion { shell } => {
-            generate_completion(shell);
+/// How the results of a command are printed, this is decided once from the command line.
+enum OutputMode {
+    /// The outputs are parsed and printed as a single table
+    Table(Table),
+    /// The logs of every container, interleaved and sorted when docker prints timestamps
+    Logs { sorted: bool },
+    /// `--grep` searches the captured logs locally
+    Grep(formatter::LogGrep),
+    /// `--export` writes the logs to a directory instead of printing them
+    Export(std::path::PathBuf),
+    /// `inspect` prints a single JSON array unless the user asked for a format of their own
+    Inspect,
+    /// The output of every container or node is followed by a summary of the exit codes
+    FanOut,
+    /// The output of every node is printed as is
+    Plain,
+}
+
+impl OutputMode {
+    fn new(command: &cli::Command) -> Self {
+        match command {
+            // `ps` and `images` only print JSON lines when the user did not ask for a format of
+            // their own
+            cli::Command::Ps {
+                format: None,
+                quiet: false,
+                size,
+                ..
+            } => Self::Table(Table::Containers { size: *size }),
+            cli::Command::Images {
+                format: None,
+                quiet: false,
+                digest,
+                ..
+            } => Self::Table(Table::Images { digests: *digest }),
+            cli::Command::Stats { sort, .. } => Self::Table(Table::Stats { sort: *sort }),
+            cli::Command::Top { .. } => Self::Table(Table::Processes),
+            cli::Command::Logs {
+                grep: Some(pattern),
+                context,
+                ..
+            } => Self::Grep(formatter::LogGrep::new(pattern, *context)),
+            cli::Command::Logs {
+                export: Some(dir), ..
+            } => Self::Export(std::path::PathBuf::from(dir)),
+            cli::Command::Logs { timestamps, .. } => Self::Logs {
+                sorted: *timestamps,
+            },
+            cli::Command::Inspect { format: None, .. } => Self::Inspect,
+            cli::Command::Exec {
+                all_matches: true, ..
+            }
+            | cli::Command::HostExec { .. } => Self::FanOut,
+            _ => Self::Plain,
         }
-        _ => {
-            command = _cli.command.to_string();
-            match &_cli.command {
-                cli::Command::Ps { .. } => parse = true,
-                cli::Command::Images { .. } => parse = true,
-                _ => (),
+    }
+
+    /// Turns a result into what the JSON formats print for it, with the parsed output as rows.
+    fn node_result(
+        &self,
+        result: &Result<utility::NodeOutput, utility::CommandError>,
+    ) -> formatter::NodeResult {
+        let parser: Option<fn(&str) -> Vec<serde_json::Value>> = match self {
+            Self::Table(Table::Containers { .. }) => {
+                Some(formatter::summary_values::<formatter::ContainerSummary>)
+            }
+            Self::Table(Table::Images { .. }) => {
+                Some(formatter::summary_values::<formatter::ImageSummary>)
+            }
+            Self::Table(Table::Stats { .. }) => {
+                Some(formatter::summary_values::<formatter::StatsSummary>)
+            }
+            Self::Inspect => Some(formatter::inspect_values),
+            _ => None,
+        };
+
+        let mut node_result = formatter::NodeResult::from_result(result, parser);
+        match (self, result) {
+            // a failed `docker logs` only printed its error, there are no logs to search
+            (Self::Grep(grep), Ok(output)) if output.success() => {
+                node_result.rows = Some(grep.rows(output))
+            }
+            // the rows of `top` are annotated with their container
+            (Self::Table(Table::Processes), Ok(output)) => {
+                node_result.rows = Some(formatter::top_values(
+                    output.container.as_deref().unwrap_or_default(),
+                    &output.stdout,
+                ))
             }
-            let internal_command = _cli.command.internal_reference_command();
-            for word in
-                utility::run_command(internal_command, _cli.sudo, regex, identity_file).await
-            {
-                match word {
-                    Ok(s) => result.push_str(&s),
-                    Err(e) => {
-                        println!("{}", e);
+            _ => (),
+        }
+
+        node_result
+    }
+
+    /// Prints the results as a table or as plain text. Errors are printed in between in the order
+    /// they were returned in.
+    fn print(&self, results: &[Result<utility::NodeOutput, utility::CommandError>]) {
+        let mut outputs: Vec<&utility::NodeOutput> = vec![];
+
+        for result in results {
+            let output = match result {
+                Ok(output) => output,
+                Err(e) => {
+                    println!("{}", e);
+                    continue;
+                }
+            };
+
+            match self {
+                Self::Table(_) | Self::Inspect => {
+                    if !output.stderr.is_empty() {
+                        eprint!("{}\n{}", output.node, output.stderr);
+                    }
+                }
+                Self::FanOut | Self::Plain => {
+                    match (&output.container, self) {
+                        (Some(container), Self::FanOut) => {
+                            println!("{}/{}", output.node, container)
+                        }
+                        _ => println!("{}", output.node),
                     }
+                    print!("{}", output.stdout);
+                    eprint!("{}", output.stderr);
                 }
+                Self::Logs { .. } | Self::Grep(_) | Self::Export(_) => (),
+            }
+            outputs.push(output);
+        }
+
+        let color = std::io::stdout().is_terminal();
+        match self {
+            Self::Table(table) => table.print(&outputs),
+            Self::Logs { sorted } => formatter::print_logs(&outputs, color, *sorted),
+            Self::Grep(grep) => formatter::print_grep(&outputs, grep, color),
+            Self::Inspect => {
+                let inspected: Vec<(&str, &str)> = outputs
+                    .iter()
+                    .map(|output| (output.node.as_str(), output.stdout.as_str()))
+                    .collect();
+                // serializing values that were just parsed can not fail
+                let merged = formatter::merge_inspect(&inspected);
+                println!("{}", serde_json::to_string_pretty(&merged).unwrap());
             }
+            Self::FanOut => formatter::Parser::from_exec_results(&outputs).print(),
+            Self::Plain | Self::Export(_) => (),
         }
     }
+}
 
-    if parse {
-        let mut parser: Option<formatter::Parser> = None;
+/// Commands whose output is parsed and printed as a single table.
+enum Table {
+    Containers { size: bool },
+    Images { digests: bool },
+    Stats { sort: Option<formatter::StatsSort> },
+    Processes,
+}
 
-        match command.as_str() {
-            "Ps" => parser = Some(formatter::Parser::from_ps_results(&result)),
-            "Images" => parser = Some(formatter::Parser::from_images_results(&result)),
+impl Table {
+    fn print(&self, outputs: &[&utility::NodeOutput]) {
+        let pairs: Vec<(&str, &str)> = outputs
+            .iter()
+            .map(|output| (output.node.as_str(), output.stdout.as_str()))
+            .collect();
 
-            _ => (),
+        match self {
+            Self::Containers { size } => formatter::Parser::from_ps_results(&pairs, *size).print(),
+            Self::Images { digests } => {
+                formatter::Parser::from_images_results(&pairs, *digests).print()
+            }
+            Self::Stats { sort } => formatter::Parser::from_stats_results(&pairs, *sort).print(),
+            Self::Processes => formatter::Parser::from_top_results(outputs).print(),
         }
+    }
+}
 
-        if let Some(mut parser) = parser {
-            parser.print();
+fn stats_once(command: &cli::Command, output: formatter::OutputFormat) -> bool {
+    match command {
+        cli::Command::Stats { no_stream, .. } => {
+            *no_stream || output != formatter::OutputFormat::Table
         }
-    } else {
-        println!("{}", result);
+        _ => true,
+    }
+}
+
+/// Runs `stats` on every node over and over and redraws the table after every sweep, until
+/// ctrl-c is pressed.
+async fn watch_stats(
+    container_id: Vec<&str>,
+    all: bool,
+    no_trunc: bool,
+    selector: &client::NodeSelector<'_>,
+    options: &client::RunOptions<'_>,
+    sort: Option<formatter::StatsSort>,
+) -> i32 {
+    let redraw = |errors: &[utility::CommandError],
+                  results: &[Result<utility::NodeOutput, utility::CommandError>]| {
+        let outputs: Vec<(&str, &str)> = results
+            .iter()
+            .flatten()
+            .map(|output| (output.node.as_str(), output.stdout.as_str()))
+            .collect();
+
+        // clear the screen and move the cursor to the top left, like docker stats does
+        print!("\x1b[2J\x1b[H");
+        let failed = results.iter().filter_map(|result| result.as_ref().err());
+        for error in errors.iter().chain(failed) {
+            println!("{}", error);
+        }
+        formatter::Parser::from_stats_results(&outputs, sort).print();
+    };
+    let watch = utility::watch_stats(
+        container_id,
+        all,
+        no_trunc,
+        selector,
+        options,
+        std::time::Duration::from_secs(constants::STATS_INTERVAL),
+        redraw,
+    );
+
+    // ctrl-c is how watching is supposed to end
+    let results = tokio::select! {
+        results = watch => results,
+        _ = tokio::signal::ctrl_c() => return 0,
+    };
+
+    // none of the containers were found, nothing would show up on a later sweep
+    for error in results.iter().filter_map(|result| result.as_ref().err()) {
+        eprintln!("{}", error);
     }
+    utility::ExitCode::from_results(&results) as i32
 }
 
 fn generate_completion(shell: clap_complete::Shell) {
@@ -1,4 +1,5 @@
 #[tokio::main]
 async fn main() {
-    docker_manager::run().await;
+    let code = docker_manager::run().await;
+    std::process::exit(code);
 }
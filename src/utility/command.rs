@@ -1,14 +1,409 @@
-use crate::cli::flags::{ExecFlags, ImagesFlags, LogsFlags, PsFlags, RmFlags};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::OnceCell;
+
+use crate::cli::flags::{
+    ExecFlags, ImagesFlags, InspectFlags, LogsFlags, PsFlags, RmFlags, RunFlags, StatsFlags,
+};
 use crate::cli::{System, SystemCommand};
+use crate::formatter::LogSink;
+
+/// The result of running a command on a single node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeOutput {
+    /// Address of the node the command ran on
+    pub node: String,
+    /// Name of the container the command ran against, for commands on a single container
+    pub container: Option<String>,
+    /// This is `None` when the remote process was killed by a signal
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration: Duration,
+}
+
+impl NodeOutput {
+    pub fn new(node: &str, output: std::process::Output, duration: Duration) -> Self {
+        Self {
+            node: node.to_string(),
+            container: None,
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            duration,
+        }
+    }
+
+    /// Returns true if the remote command exited with 0.
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Decides if docker is ran through sudo on a remote node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sudo {
+    Always,
+    Never,
+    /// Run docker with `sudo -n` only if `docker version` can not access the docker socket
+    Auto,
+}
+
+/// A process that was started on a remote node, it keeps running when the command that started
+/// it is dropped locally.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RemoteProcess {
+    pid: u32,
+    /// The process is killed with the same privileges it was started with
+    sudo: Sudo,
+}
+
+impl RemoteProcess {
+    pub async fn kill(&self, session: &openssh::Session) -> Result<(), openssh::Error> {
+        let kill = format!("{} kill {}", sudo_args(self.sudo).join(" "), self.pid);
+        session
+            .command("sh")
+            .arg("-c")
+            .arg(kill.trim())
+            .status()
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Describes how the docker binary is invoked on a remote node.
+#[derive(Clone, Copy, Debug)]
+pub struct Docker<'a> {
+    /// Path to the docker binary, this is `docker` unless the node overrides it
+    pub binary: &'a str,
+    pub sudo: Sudo,
+    /// What `Sudo::Auto` turned out to mean on the node, shared by every command on its session
+    pub probed: &'a OnceCell<Sudo>,
+    /// Where the remote process of a command is recorded, so it can be killed if the command
+    /// times out
+    pub process: Option<&'a OnceLock<RemoteProcess>>,
+}
+
+impl<'a> Docker<'a> {
+    pub fn new(
+        binary: &'a str,
+        sudo: Sudo,
+        probed: &'a OnceCell<Sudo>,
+        process: Option<&'a OnceLock<RemoteProcess>>,
+    ) -> Self {
+        Self {
+            binary,
+            sudo,
+            probed,
+            process,
+        }
+    }
+
+    /// Runs docker with the given arguments and waits for it to finish. The command itself is
+    /// never retried, with `Sudo::Auto` sudo is decided on up front.
+    async fn output(
+        &self,
+        session: &openssh::Session,
+        args: Vec<&str>,
+    ) -> Result<std::process::Output, openssh::Error> {
+        let sudo = self.resolve_sudo(session).await?;
+        let mut remote: Vec<&str> = sudo_args(sudo).to_vec();
+        remote.push(self.binary);
+        remote.extend(args);
+
+        remote_output(session, &remote, sudo, self.process).await
+    }
+
+    /// Runs docker with the given arguments on `hostname` and records how long it took.
+    async fn run(
+        &self,
+        hostname: &str,
+        session: &openssh::Session,
+        args: Vec<&str>,
+    ) -> Result<NodeOutput, openssh::Error> {
+        let start = Instant::now();
+        let output = self.output(session, args).await?;
+
+        Ok(NodeOutput::new(hostname, output, start.elapsed()))
+    }
+
+    /// Streams the output of docker line by line to `sink`, until docker exits on its own or
+    /// SIGINT is received. The first SIGINT kills the remote process,
+    /// a second one stops waiting for it.
+    async fn stream(
+        &self,
+        hostname: &str,
+        session: &openssh::Session,
+        args: Vec<&str>,
+        sink: &LogSink,
+    ) -> Result<NodeOutput, openssh::Error> {
+        let sudo = self.resolve_sudo(session).await?;
+        let mut remote: Vec<&str> = sudo_args(sudo).to_vec();
+        remote.push(self.binary);
+        remote.extend(args);
+
+        // A SIGINT that arrives before the pid is read is held until the loop below, so the
+        // remote process is killed instead of being left behind with the local side gone
+        let mut interrupt = signal(SignalKind::interrupt()).map_err(openssh::Error::ChildIo)?;
+
+        let start = Instant::now();
+        let mut child = session
+            .command("sh")
+            .arg("-c")
+            .arg(pid_script(&remote))
+            .stdin(openssh::Stdio::null())
+            .stdout(openssh::Stdio::piped())
+            .stderr(openssh::Stdio::piped())
+            .spawn()
+            .await?;
+
+        // unwrap is safe here since both were piped above
+        let mut stdout = BufReader::new(child.stdout().take().unwrap()).lines();
+        let mut stderr = BufReader::new(child.stderr().take().unwrap()).lines();
+        let process: Option<RemoteProcess> = match stdout.next_line().await {
+            Ok(Some(line)) => line
+                .trim()
+                .parse()
+                .ok()
+                .map(|pid| RemoteProcess { pid, sudo }),
+            _ => None,
+        };
+
+        let (mut stdout_open, mut stderr_open, mut interrupted) = (true, true, false);
+
+        while stdout_open || stderr_open {
+            tokio::select! {
+                line = stdout.next_line(), if stdout_open => match line {
+                    Ok(Some(line)) => sink.send(line, false),
+                    _ => stdout_open = false,
+                },
+                line = stderr.next_line(), if stderr_open => match line {
+                    Ok(Some(line)) => sink.send(line, true),
+                    _ => stderr_open = false,
+                },
+                _ = interrupt.recv() => match (interrupted, process) {
+                    (false, Some(process)) => {
+                        interrupted = true;
+                        process.kill(session).await?;
+                    }
+                    _ => {
+                        // the remote process could not be killed, stop waiting for it
+                        child.disconnect().await.map_err(openssh::Error::ChildIo)?;
+
+                        return Ok(NodeOutput {
+                            node: hostname.to_string(),
+                            container: None,
+                            exit_code: None,
+                            stdout: String::new(),
+                            stderr: String::new(),
+                            duration: start.elapsed(),
+                        });
+                    }
+                },
+            }
+        }
+
+        let status = child.wait().await?;
+
+        Ok(NodeOutput {
+            node: hostname.to_string(),
+            container: None,
+            // docker being killed is how following is supposed to end
+            exit_code: match interrupted {
+                true => Some(0),
+                false => status.code(),
+            },
+            stdout: String::new(),
+            stderr: String::new(),
+            duration: start.elapsed(),
+        })
+    }
+
+    /// Runs docker with the local stdin, stdout and stderr attached to it. This goes through the
+    /// ssh client on the control socket of `session`, since it already takes care of putting the
+    /// local terminal in raw mode and forwarding window size changes when `tty` is set. The exit
+    /// code is the one of the remote docker process.
+    async fn attach(
+        &self,
+        hostname: &str,
+        session: &openssh::Session,
+        args: Vec<&str>,
+        tty: bool,
+    ) -> Result<NodeOutput, openssh::Error> {
+        let mut remote: Vec<&str> = sudo_args(self.resolve_sudo(session).await?).to_vec();
+        remote.push(self.binary);
+        remote.extend(args);
+
+        let start = Instant::now();
+        let status = tokio::process::Command::new("ssh")
+            .arg("-S")
+            .arg(session.control_socket())
+            .arg(if tty { "-tt" } else { "-T" })
+            .arg(hostname)
+            .arg("--")
+            // ssh joins the arguments into a single command line for the remote shell
+            .args(remote.iter().map(|arg| shell_quote(arg)))
+            .status()
+            .await
+            .map_err(openssh::Error::Ssh)?;
+
+        Ok(NodeOutput {
+            node: hostname.to_string(),
+            container: None,
+            exit_code: status.code(),
+            stdout: String::new(),
+            stderr: String::new(),
+            duration: start.elapsed(),
+        })
+    }
+
+    /// Decides how docker is ran, for `Sudo::Auto` the first command on a node checks if the
+    /// docker socket is accessible with `docker version`.
+    async fn resolve_sudo(&self, session: &openssh::Session) -> Result<Sudo, openssh::Error> {
+        match self.sudo {
+            Sudo::Auto => self
+                .probed
+                .get_or_try_init(|| async {
+                    let probe = session.command(self.binary).arg("version").output().await?;
+
+                    match is_permission_denied(&probe) {
+                        true => Ok(Sudo::Auto),
+                        false => Ok(Sudo::Never),
+                    }
+                })
+                .await
+                .copied(),
+            sudo => Ok(sudo),
+        }
+    }
+}
+
+/// Returns the command docker is prefixed with for the given sudo setting.
+fn sudo_args(sudo: Sudo) -> &'static [&'static str] {
+    match sudo {
+        Sudo::Always => &["sudo"],
+        Sudo::Never => &[],
+        Sudo::Auto => &["sudo", "-n"],
+    }
+}
+
+/// Returns a shell script that prints the pid of the shell before it is replaced by `remote`, so
+/// the remote process can be killed later.
+fn pid_script(remote: &[&str]) -> String {
+    format!(
+        "echo $$; exec {}",
+        remote
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<String>>()
+            .join(" ")
+    )
+}
+
+/// Runs `remote` and waits for it to finish. When `process` is given the remote process is
+/// recorded in it as soon as it started.
+async fn remote_output(
+    session: &openssh::Session,
+    remote: &[&str],
+    sudo: Sudo,
+    process: Option<&OnceLock<RemoteProcess>>,
+) -> Result<std::process::Output, openssh::Error> {
+    let process = match process {
+        Some(process) => process,
+        None => return session.command(remote[0]).args(&remote[1..]).output().await,
+    };
+
+    let mut child = session
+        .command("sh")
+        .arg("-c")
+        .arg(pid_script(remote))
+        .stdin(openssh::Stdio::null())
+        .stdout(openssh::Stdio::piped())
+        .stderr(openssh::Stdio::piped())
+        .spawn()
+        .await?;
+
+    // unwrap is safe here since both were piped above
+    let mut stdout = BufReader::new(child.stdout().take().unwrap());
+    let mut stderr = child.stderr().take().unwrap();
+
+    let mut pid = String::new();
+    stdout
+        .read_line(&mut pid)
+        .await
+        .map_err(openssh::Error::ChildIo)?;
+    if let Ok(pid) = pid.trim().parse() {
+        let _ = process.set(RemoteProcess { pid, sudo });
+    }
+
+    let (mut out, mut err) = (vec![], vec![]);
+    tokio::try_join!(stdout.read_to_end(&mut out), stderr.read_to_end(&mut err))
+        .map_err(openssh::Error::ChildIo)?;
+
+    Ok(std::process::Output {
+        status: child.wait().await?,
+        stdout: out,
+        stderr: err,
+    })
+}
+
+/// Quotes `arg` so the remote shell passes it on as a single argument.
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c);
+
+    match !arg.is_empty() && arg.chars().all(safe) {
+        true => arg.to_string(),
+        false => format!("'{}'", arg.replace('\'', "'\\''")),
+    }
+}
+
+/// Checks if docker failed because the user is not allowed to use the docker socket.
+fn is_permission_denied(output: &std::process::Output) -> bool {
+    let stderr = String::from_utf8_lossy(&output.stderr).to_lowercase();
+
+    !output.status.success()
+        && stderr.contains("permission denied while trying to connect to the docker daemon socket")
+}
+
+/// Runs `docker run` or, with `create` set, `docker create`.
+pub async fn run_run(
+    hostname: &str,
+    session: &openssh::Session,
+    docker: Docker<'_>,
+    create: bool,
+    image: &str,
+    command: &[String],
+    flags: RunFlags<'_>,
+) -> Result<NodeOutput, openssh::Error> {
+    let mut _command: Vec<&str> = match create {
+        true => vec!["create"],
+        false => vec!["run"],
+    };
+
+    for flag in flags.flags() {
+        _command.push(flag);
+    }
+    _command.push(image);
+
+    for arg in command {
+        _command.push(arg);
+    }
+
+    docker.run(hostname, session, _command).await
+}
 
 pub async fn run_exec(
     hostname: &str,
-    session: openssh::Session,
+    session: &openssh::Session,
     container_id: &str,
-    sudo: bool,
+    docker: Docker<'_>,
     command: Vec<&str>,
     flags: ExecFlags<'_>,
-) -> Result<String, openssh::Error> {
+) -> Result<NodeOutput, openssh::Error> {
     let mut _command: Vec<&str> = vec!["exec"];
 
     for flag in &flags.flags() {
@@ -20,197 +415,163 @@ pub async fn run_exec(
         _command.push(arg);
     }
 
-    if flags.interactive {
-        // This needs to be mutable so the stdout can be written to
-        #[allow(unused_mut)]
-        let mut _output = match sudo {
-            true => {
-                session
-                    .command("sudo")
-                    .arg("docker")
-                    .args(_command)
-                    .spawn()
-                    .await?
-            }
-            false => session.command("docker").args(_command).spawn().await?,
-        };
-
-        loop {
-            std::thread::sleep(std::time::Duration::new(1, 0));
-        }
+    if flags.interactive || flags.tty {
+        docker.attach(hostname, session, _command, flags.tty).await
     } else {
-        let _output = match sudo {
-            true => {
-                session
-                    .command("sudo")
-                    .arg("docker")
-                    .args(_command)
-                    .output()
-                    .await
-            }
-            false => session.command("docker").args(_command).output().await,
-        };
+        docker.run(hostname, session, _command).await
+    }
+}
 
-        let output = match _output {
-            Ok(output) => output,
-            Err(e) => return Err(e),
-        };
+/// Runs `command` on the node itself. The arguments are joined into a single command line for
+/// the remote shell like ssh does, so pipes and globs work as they would in a terminal.
+pub async fn run_host_exec(
+    hostname: &str,
+    session: &openssh::Session,
+    sudo: bool,
+    command: Vec<&str>,
+    process: Option<&OnceLock<RemoteProcess>>,
+) -> Result<NodeOutput, openssh::Error> {
+    let sudo = match sudo {
+        true => Sudo::Always,
+        false => Sudo::Never,
+    };
+    let script = command.join(" ");
+    let mut remote: Vec<&str> = sudo_args(sudo).to_vec();
+    remote.extend(["sh", "-c", &script]);
 
-        let mut rv: String = format!("{}\n", hostname);
-        match output.status.code().unwrap() {
-            0 => rv.push_str(std::str::from_utf8(&output.stdout).unwrap_or("")),
-            _ => rv.push_str(std::str::from_utf8(&output.stderr).unwrap_or("")),
-        };
-        Ok(rv)
-    }
+    let start = Instant::now();
+    let output = remote_output(session, &remote, sudo, process).await?;
+
+    Ok(NodeOutput::new(hostname, output, start.elapsed()))
 }
 
 pub async fn run_images(
     hostname: &str,
-    session: openssh::Session,
-    sudo: bool,
+    session: &openssh::Session,
+    docker: Docker<'_>,
     flags: ImagesFlags<'_>,
-) -> Result<String, openssh::Error> {
+) -> Result<NodeOutput, openssh::Error> {
     let mut command: Vec<&str> = vec!["images"];
 
     for flag in flags.flags() {
         command.push(flag)
     }
 
-    let _output = match sudo {
-        true => {
-            session
-                .command("sudo")
-                .arg("docker")
-                .args(command)
-                .output()
-                .await
-        }
-        false => session.command("docker").args(command).output().await,
-    };
+    docker.run(hostname, session, command).await
+}
 
-    let output = match _output {
-        Ok(output) => output,
-        Err(e) => return Err(e),
-    };
+pub async fn run_inspect(
+    hostname: &str,
+    session: &openssh::Session,
+    docker: Docker<'_>,
+    container_id: &[&str],
+    flags: InspectFlags<'_>,
+) -> Result<NodeOutput, openssh::Error> {
+    let mut command: Vec<&str> = vec!["inspect"];
 
-    let mut rv: String = format!("{}\n", hostname);
-    match output.status.code().unwrap() {
-        0 => rv.push_str(std::str::from_utf8(&output.stdout).unwrap_or("")),
-        _ => rv.push_str(std::str::from_utf8(&output.stderr).unwrap_or("")),
-    };
+    for flag in flags.flags() {
+        command.push(flag)
+    }
 
-    Ok(rv)
+    for container in container_id {
+        command.push(container);
+    }
+
+    docker.run(hostname, session, command).await
+}
+
+pub async fn run_kill(
+    hostname: &str,
+    session: &openssh::Session,
+    docker: Docker<'_>,
+    container_id: &Vec<&str>,
+    signal: Option<&str>,
+) -> Result<NodeOutput, openssh::Error> {
+    docker
+        .run(hostname, session, kill_args(container_id, signal))
+        .await
+}
+
+/// Returns the arguments of `docker kill` for the given containers.
+fn kill_args<'a>(container_id: &[&'a str], signal: Option<&'a str>) -> Vec<&'a str> {
+    let mut command = vec!["kill"];
+
+    if let Some(signal) = signal {
+        command.push("--signal");
+        command.push(signal);
+    }
+
+    for container in container_id {
+        command.push(container);
+    }
+
+    command
 }
 
 pub async fn run_logs(
     hostname: &str,
-    session: openssh::Session,
+    session: &openssh::Session,
     container_id: &str,
-    sudo: bool,
+    docker: Docker<'_>,
     flags: LogsFlags<'_>,
-) -> Result<String, openssh::Error> {
+    sink: Option<&LogSink>,
+) -> Result<NodeOutput, openssh::Error> {
     let mut command: Vec<&str> = vec!["logs"];
 
     for item in flags.flags() {
         command.push(item)
     }
 
-    if flags.follow {
+    // Followed logs never end on their own so they can only be streamed, not captured
+    if let (true, Some(sink)) = (flags.follow, sink) {
         command.push("-f");
         command.push(container_id);
 
-        // This needs to be mutable so the stdout can be written to
-        #[allow(unused_mut)]
-        let mut _output = match sudo {
-            true => {
-                session
-                    .command("sudo")
-                    .arg("docker")
-                    .args(command)
-                    .spawn()
-                    .await?
-            }
-            false => session.command("docker").args(command).spawn().await?,
-        };
-
-        loop {
-            std::thread::sleep(std::time::Duration::new(1, 0));
-        }
+        docker.stream(hostname, session, command, sink).await
     } else {
         command.push(container_id);
-        let _output = match sudo {
-            true => {
-                session
-                    .command("sudo")
-                    .arg("docker")
-                    .args(command)
-                    .output()
-                    .await
-            }
-            false => session.command("docker").args(command).output().await,
-        };
-
-        let output = match _output {
-            Ok(output) => output,
-            Err(e) => return Err(e),
-        };
-
-        let mut rv: String = format!("{}\n", hostname);
 
-        // docker logs prints the logs to standard error so it does not matter if the command
-        // succeeded or not
-        rv.push_str(std::str::from_utf8(&output.stderr).unwrap_or(""));
+        // docker logs forwards the stdout and stderr of the container as is, so both are kept
+        docker.run(hostname, session, command).await
+    }
+}
 
-        Ok(rv)
+pub async fn run_pause(
+    hostname: &str,
+    session: &openssh::Session,
+    docker: Docker<'_>,
+    container_id: &Vec<&str>,
+) -> Result<NodeOutput, openssh::Error> {
+    let mut command = vec!["pause"];
+    for container in container_id {
+        command.push(container);
     }
+
+    docker.run(hostname, session, command).await
 }
 
 pub async fn run_ps(
     hostname: &str,
-    session: openssh::Session,
-    sudo: bool,
+    session: &openssh::Session,
+    docker: Docker<'_>,
     flags: PsFlags<'_>,
-) -> Result<String, openssh::Error> {
+) -> Result<NodeOutput, openssh::Error> {
     let mut command: Vec<&str> = vec!["ps"];
 
     for flag in flags.flags() {
         command.push(flag)
     }
 
-    let _output = match sudo {
-        true => {
-            session
-                .command("sudo")
-                .arg("docker")
-                .args(command)
-                .output()
-                .await
-        }
-        false => session.command("docker").args(command).output().await,
-    };
-
-    let output = match _output {
-        Ok(output) => output,
-        Err(e) => return Err(e),
-    };
-
-    let mut rv: String = format!("{}\n", hostname);
-    match output.status.code().unwrap() {
-        0 => rv.push_str(std::str::from_utf8(&output.stdout).unwrap_or("")),
-        _ => rv.push_str(std::str::from_utf8(&output.stderr).unwrap_or("")),
-    };
-
-    Ok(rv)
+    docker.run(hostname, session, command).await
 }
 
 pub async fn run_restart(
     hostname: &str,
-    session: openssh::Session,
-    sudo: bool,
+    session: &openssh::Session,
+    docker: Docker<'_>,
     time: Option<&str>,
     container_id: &[&str],
-) -> Result<String, openssh::Error> {
+) -> Result<NodeOutput, openssh::Error> {
     let mut command = vec!["restart"];
 
     for container in container_id {
@@ -225,39 +586,16 @@ pub async fn run_restart(
         command.push("10");
     }
 
-    let _output = match sudo {
-        true => {
-            session
-                .command("sudo")
-                .arg("docker")
-                .args(command)
-                .output()
-                .await
-        }
-        false => session.command("docker").args(command).output().await,
-    };
-
-    let output = match _output {
-        Ok(output) => output,
-        Err(e) => return Err(e),
-    };
-
-    let mut rv: String = format!("{}\n", hostname);
-    match output.status.code().unwrap() {
-        0 => rv.push_str(std::str::from_utf8(&output.stdout).unwrap_or("")),
-        _ => rv.push_str(std::str::from_utf8(&output.stderr).unwrap_or("")),
-    };
-
-    Ok(rv)
+    docker.run(hostname, session, command).await
 }
 
 pub async fn run_rm(
     hostname: &str,
-    session: openssh::Session,
-    sudo: bool,
+    session: &openssh::Session,
+    docker: Docker<'_>,
     container_id: &Vec<&str>,
     flags: RmFlags,
-) -> Result<String, openssh::Error> {
+) -> Result<NodeOutput, openssh::Error> {
     let mut command = vec!["rm"];
 
     for container in container_id {
@@ -268,39 +606,16 @@ pub async fn run_rm(
         command.push(flag)
     }
 
-    let _output = match sudo {
-        true => {
-            session
-                .command("sudo")
-                .arg("docker")
-                .args(command)
-                .output()
-                .await
-        }
-        false => session.command("docker").args(command).output().await,
-    };
-
-    let output = match _output {
-        Ok(output) => output,
-        Err(e) => return Err(e),
-    };
-
-    let mut rv: String = format!("{}\n", hostname);
-    match output.status.code().unwrap() {
-        0 => rv.push_str(std::str::from_utf8(&output.stdout).unwrap_or("")),
-        _ => rv.push_str(std::str::from_utf8(&output.stderr).unwrap_or("")),
-    };
-
-    Ok(rv)
+    docker.run(hostname, session, command).await
 }
 
 pub async fn run_start(
     hostname: &str,
-    session: openssh::Session,
-    sudo: bool,
+    session: &openssh::Session,
+    docker: Docker<'_>,
     container_id: &Vec<&str>,
     attatch: bool,
-) -> Result<String, openssh::Error> {
+) -> Result<NodeOutput, openssh::Error> {
     let mut command = vec!["start"];
     for container in container_id {
         command.push(container);
@@ -310,75 +625,49 @@ pub async fn run_start(
         command.push("-a");
     };
 
-    let _output = match sudo {
-        true => {
-            session
-                .command("sudo")
-                .arg("docker")
-                .args(command)
-                .output()
-                .await
-        }
-        false => session.command("docker").args(command).output().await,
-    };
+    docker.run(hostname, session, command).await
+}
 
-    let output = match _output {
-        Ok(output) => output,
-        Err(e) => return Err(e),
-    };
+pub async fn run_stats(
+    hostname: &str,
+    session: &openssh::Session,
+    docker: Docker<'_>,
+    container_id: &[&str],
+    flags: StatsFlags,
+) -> Result<NodeOutput, openssh::Error> {
+    let mut command: Vec<&str> = vec!["stats"];
 
-    let mut rv: String = format!("{}\n", hostname);
-    match output.status.code().unwrap() {
-        0 => rv.push_str(std::str::from_utf8(&output.stdout).unwrap_or("")),
-        _ => rv.push_str(std::str::from_utf8(&output.stderr).unwrap_or("")),
-    };
+    for flag in flags.flags() {
+        command.push(flag)
+    }
+
+    for container in container_id {
+        command.push(container);
+    }
 
-    Ok(rv)
+    docker.run(hostname, session, command).await
 }
 
 pub async fn run_stop(
     hostname: &str,
-    session: openssh::Session,
-    sudo: bool,
+    session: &openssh::Session,
+    docker: Docker<'_>,
     container_id: &Vec<&str>,
-) -> Result<String, openssh::Error> {
+) -> Result<NodeOutput, openssh::Error> {
     let mut command = vec!["stop"];
     for container in container_id {
         command.push(container);
     }
 
-    let _output = match sudo {
-        true => {
-            session
-                .command("sudo")
-                .arg("docker")
-                .args(command)
-                .output()
-                .await
-        }
-        false => session.command("docker").args(command).output().await,
-    };
-
-    let output = match _output {
-        Ok(output) => output,
-        Err(e) => return Err(e),
-    };
-
-    let mut rv: String = format!("{}\n", hostname);
-    match output.status.code().unwrap() {
-        0 => rv.push_str(std::str::from_utf8(&output.stdout).unwrap_or("")),
-        _ => rv.push_str(std::str::from_utf8(&output.stderr).unwrap_or("")),
-    };
-
-    Ok(rv)
+    docker.run(hostname, session, command).await
 }
 
 pub async fn run_system(
     hostname: &str,
-    session: openssh::Session,
-    sudo: bool,
+    session: &openssh::Session,
+    docker: Docker<'_>,
     command: System,
-) -> Result<String, openssh::Error> {
+) -> Result<NodeOutput, openssh::Error> {
     let mut _command: Vec<&str> = vec!["system"];
 
     match command.command {
@@ -450,28 +739,95 @@ pub async fn run_system(
         }
     }
 
-    let _output = match sudo {
-        true => {
-            session
-                .command("sudo")
-                .arg("docker")
-                .args(_command)
-                .output()
-                .await
+    docker.run(hostname, session, _command).await
+}
+
+pub async fn run_top(
+    hostname: &str,
+    session: &openssh::Session,
+    docker: Docker<'_>,
+    container_id: &str,
+    options: &[&str],
+) -> Result<NodeOutput, openssh::Error> {
+    let mut command: Vec<&str> = vec!["top", container_id];
+
+    for option in options {
+        command.push(option);
+    }
+
+    docker.run(hostname, session, command).await
+}
+
+pub async fn run_unpause(
+    hostname: &str,
+    session: &openssh::Session,
+    docker: Docker<'_>,
+    container_id: &Vec<&str>,
+) -> Result<NodeOutput, openssh::Error> {
+    let mut command = vec!["unpause"];
+    for container in container_id {
+        command.push(container);
+    }
+
+    docker.run(hostname, session, command).await
+}
+
+#[cfg(test)]
+mod test {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+
+    use super::{is_permission_denied, kill_args, pid_script, shell_quote};
+
+    fn output(code: i32, stderr: &str) -> Output {
+        Output {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: vec![],
+            stderr: stderr.as_bytes().to_vec(),
         }
-        false => session.command("docker").args(_command).output().await,
-    };
+    }
 
-    let output = match _output {
-        Ok(output) => output,
-        Err(e) => return Err(e),
-    };
+    #[test]
+    fn test_is_permission_denied() {
+        assert!(is_permission_denied(&output(
+            1,
+            "Got permission denied while trying to connect to the Docker daemon socket at \
+            unix:///var/run/docker.sock: Get \"http://%2Fvar%2Frun%2Fdocker.sock/v1.24/containers/json\""
+        )));
+        assert!(!is_permission_denied(&output(
+            1,
+            "Error: No such container: abc"
+        )));
+        assert!(!is_permission_denied(&output(
+            1,
+            "cat: /etc/docker/daemon.json: Permission denied"
+        )));
+        assert!(!is_permission_denied(&output(0, "")));
+    }
 
-    let mut rv: String = format!("{}\n", hostname);
-    match output.status.code().unwrap() {
-        0 => rv.push_str(std::str::from_utf8(&output.stdout).unwrap_or("")),
-        _ => rv.push_str(std::str::from_utf8(&output.stderr).unwrap_or("")),
-    };
+    #[test]
+    fn test_kill_args() {
+        assert_eq!(vec!["kill", "web", "db"], kill_args(&["web", "db"], None));
+        assert_eq!(
+            vec!["kill", "--signal", "SIGHUP", "web"],
+            kill_args(&["web"], Some("SIGHUP"))
+        );
+    }
 
-    Ok(rv)
+    #[test]
+    fn test_pid_script() {
+        assert_eq!(
+            "echo $$; exec sudo -n docker ps --format '{{json .}}'",
+            pid_script(&["sudo", "-n", "docker", "ps", "--format", "{{json .}}"])
+        );
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!("bash", shell_quote("bash"));
+        assert_eq!("--env=A=1", shell_quote("--env=A=1"));
+        assert_eq!("''", shell_quote(""));
+        assert_eq!("'echo $HOME'", shell_quote("echo $HOME"));
+        assert_eq!("'it'\\''s'", shell_quote("it's"));
+    }
 }
@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::{CommandError, NodeOutput};
+
+/// Name of the file in the export directory that lists what was collected.
+pub const MANIFEST: &str = "manifest.json";
+
+/// An entry of the manifest, either a log file that was written or a container or node whose
+/// logs could not be collected.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ExportEntry {
+    /// This is `None` for errors that are not tied to a node, like a container not being found
+    pub hostname: Option<String>,
+    pub container: Option<String>,
+    /// Path of the log file relative to the export directory
+    pub file: Option<String>,
+    pub lines: usize,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+impl ExportEntry {
+    pub fn failed(&self) -> bool {
+        !self.success
+    }
+}
+
+/// Writes the logs of every container to `<dir>/<host>/<container>.log` and a manifest of all
+/// results to `<dir>/manifest.json`. The logs need to have been fetched with timestamps so the
+/// stdout and stderr of a container can be merged into a single file.
+pub fn export_logs(
+    dir: &Path,
+    results: &[Result<NodeOutput, CommandError>],
+) -> std::io::Result<Vec<ExportEntry>> {
+    let mut entries: Vec<ExportEntry> = vec![];
+
+    for result in results {
+        let entry = match result {
+            // the stderr of a failed `docker logs` is its error, not the logs of the container
+            Ok(output) if !output.success() => ExportEntry {
+                hostname: Some(output.node.clone()),
+                container: output.container.clone(),
+                file: None,
+                lines: 0,
+                success: false,
+                error: Some(output.stderr.trim_end().to_string()),
+            },
+            Ok(output) => {
+                let container = output.container.as_deref().unwrap_or_default();
+                let file: PathBuf = [&output.node, &format!("{}.log", container)]
+                    .iter()
+                    .collect();
+                let lines = merge_lines(output);
+
+                std::fs::create_dir_all(dir.join(&output.node))?;
+                std::fs::write(dir.join(&file), lines.join(""))?;
+
+                ExportEntry {
+                    hostname: Some(output.node.clone()),
+                    container: output.container.clone(),
+                    file: Some(file.to_string_lossy().into_owned()),
+                    lines: lines.len(),
+                    success: true,
+                    error: None,
+                }
+            }
+            Err(e) => ExportEntry {
+                hostname: e.hostname().map(String::from),
+                container: None,
+                file: None,
+                lines: 0,
+                success: false,
+                error: Some(e.to_string()),
+            },
+        };
+
+        entries.push(entry);
+    }
+
+    std::fs::create_dir_all(dir)?;
+    // serializing a list of strings and numbers can not fail
+    std::fs::write(
+        dir.join(MANIFEST),
+        serde_json::to_string_pretty(&entries).unwrap(),
+    )?;
+
+    Ok(entries)
+}
+
+/// Merges the stdout and stderr of a container in timestamp order. Every line ends in a newline,
+/// docker does not print one after the last line of a stream that was cut off.
+fn merge_lines(output: &NodeOutput) -> Vec<String> {
+    let mut lines: Vec<String> = output
+        .stdout
+        .lines()
+        .chain(output.stderr.lines())
+        .map(|line| format!("{}\n", line))
+        .collect();
+    // timestamps have a fixed number of digits, a stable sort keeps lines with the same time in
+    // the order docker printed them
+    lines.sort_by(|a, b| timestamp(a).cmp(timestamp(b)));
+
+    lines
+}
+
+/// The timestamp `docker logs --timestamps` puts in front of a line.
+fn timestamp(line: &str) -> &str {
+    line.split(' ').next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{export_logs, merge_lines, NodeOutput, MANIFEST};
+
+    #[test]
+    fn test_merge_lines() {
+        let output = NodeOutput {
+            node: "abc".into(),
+            container: Some("web".into()),
+            exit_code: Some(0),
+            stdout:
+                "2023-03-01T10:00:01.000000000Z started\n2023-03-01T10:00:03.000000000Z ready\n"
+                    .into(),
+            stderr: "2023-03-01T10:00:02.000000000Z warning\n".into(),
+            duration: Duration::ZERO,
+        };
+
+        assert_eq!(
+            vec![
+                "2023-03-01T10:00:01.000000000Z started\n",
+                "2023-03-01T10:00:02.000000000Z warning\n",
+                "2023-03-01T10:00:03.000000000Z ready\n",
+            ],
+            merge_lines(&output)
+        );
+    }
+
+    #[test]
+    fn test_merge_lines_without_newline() {
+        let output = NodeOutput {
+            node: "abc".into(),
+            container: Some("web".into()),
+            exit_code: Some(0),
+            stdout: "2023-03-01T10:00:01.000000000Z started\n2023-03-01T10:00:03.000000000Z ready"
+                .into(),
+            stderr: "2023-03-01T10:00:02.000000000Z warning".into(),
+            duration: Duration::ZERO,
+        };
+
+        assert_eq!(
+            vec![
+                "2023-03-01T10:00:01.000000000Z started\n",
+                "2023-03-01T10:00:02.000000000Z warning\n",
+                "2023-03-01T10:00:03.000000000Z ready\n",
+            ],
+            merge_lines(&output)
+        );
+    }
+
+    #[test]
+    fn test_export_failed_logs() {
+        let dir = std::env::temp_dir().join(format!("docker-manager-{}", std::process::id()));
+        let output = NodeOutput {
+            node: "abc".into(),
+            container: Some("web".into()),
+            exit_code: Some(1),
+            stdout: String::new(),
+            stderr: "Error response from daemon: No such container: web\n".into(),
+            duration: Duration::ZERO,
+        };
+
+        let entries = export_logs(&dir, &[Ok(output)]).unwrap();
+
+        assert_eq!(None, entries[0].file);
+        assert_eq!(
+            Some("Error response from daemon: No such container: web"),
+            entries[0].error.as_deref()
+        );
+        assert!(!dir.join("abc").exists());
+        assert!(dir.join(MANIFEST).exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
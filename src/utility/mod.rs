@@ -1,6 +1,9 @@
 pub mod command;
+mod export;
 mod other;
 mod run;
 
-pub use other::find_containers;
-pub use run::run_command;
+pub use command::NodeOutput;
+pub use export::{export_logs, ExportEntry, MANIFEST};
+pub use other::{config_dir, expand_tilde, find_containers, home_dir, list_containers, MatchMode};
+pub use run::{run_command, watch_stats, CommandError, ExitCode};
@@ -1,194 +1,639 @@
-use std::collections::HashMap;
-
-use crate::constants;
+use std::path::PathBuf;
 
 use crate::cli::InternalCommand;
-use crate::client::{Client, NodeError};
+use crate::client::{pattern_match, Client, NodeError, RunOptions};
+use crate::formatter::{parse_json_lines, ContainerSummary};
+use crate::utility::{CommandError, NodeOutput};
 
 use futures::{stream, StreamExt};
+use regex::Regex;
+
+/// How the container ids given on the command line are matched against the containers on
+/// the nodes.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    /// The exact container name or a prefix of the container id, like docker does
+    #[default]
+    Exact,
+    /// A prefix of the container name or id
+    Prefix,
+    /// A glob pattern for the container name, `*` matches anything and `?` a single character
+    Glob,
+    /// A regular expression for the container name
+    Regex,
+}
+
+/// The rule a container was matched by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchRule {
+    IdPrefix,
+    Name,
+    NamePrefix,
+    Glob,
+    Regex,
+}
+
+impl std::fmt::Display for MatchRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::IdPrefix => write!(f, "id prefix"),
+            Self::Name => write!(f, "name"),
+            Self::NamePrefix => write!(f, "name prefix"),
+            Self::Glob => write!(f, "glob"),
+            Self::Regex => write!(f, "regex"),
+        }
+    }
+}
 
-/// This function takes a `Client` and returns a list of matched node names in the form of a
-/// Vec of `Container`.
+/// A container id from the command line compiled for the given `MatchMode`.
+enum Matcher<'a> {
+    Exact(&'a str),
+    Prefix(&'a str),
+    Glob(&'a str),
+    Regex(Regex),
+}
+
+impl<'a> Matcher<'a> {
+    fn new(query: &'a str, mode: MatchMode) -> Self {
+        match mode {
+            MatchMode::Exact => Self::Exact(query),
+            MatchMode::Prefix => Self::Prefix(query),
+            MatchMode::Glob => Self::Glob(query),
+            MatchMode::Regex => match Regex::new(query) {
+                Ok(regex) => Self::Regex(regex),
+                Err(e) => {
+                    eprintln!(
+                        "Some error has occured while compiling your regex patterns {}\n{}",
+                        query, e
+                    );
+                    std::process::exit(1)
+                }
+            },
+        }
+    }
+
+    /// Returns the rule that matched `container`, if any. Names are compared before ids, a
+    /// container with multiple names matches if any of them does.
+    fn matches(&self, container: &ContainerSummary) -> Option<MatchRule> {
+        let mut names = container.names.split(',').map(str::trim);
+
+        match self {
+            Self::Exact(query) => {
+                if names.any(|name| name == *query) {
+                    Some(MatchRule::Name)
+                } else if !query.is_empty() && container.id.starts_with(query) {
+                    Some(MatchRule::IdPrefix)
+                } else {
+                    None
+                }
+            }
+            Self::Prefix(query) => {
+                if names.any(|name| name.starts_with(query)) {
+                    Some(MatchRule::NamePrefix)
+                } else if !query.is_empty() && container.id.starts_with(query) {
+                    Some(MatchRule::IdPrefix)
+                } else {
+                    None
+                }
+            }
+            Self::Glob(pattern) => names
+                .any(|name| pattern_match(pattern, name))
+                .then_some(MatchRule::Glob),
+            Self::Regex(regex) => names
+                .any(|name| regex.is_match(name))
+                .then_some(MatchRule::Regex),
+        }
+    }
+}
+
+/// This function takes a `Client` and returns the matched containers grouped by node in the
+/// form of a `Lookup`.
 pub async fn find_containers(
-    client: Client,
+    client: &Client,
     container_ids: &[&str],
-    sudo: bool,
     all: bool,
-    identity_file: Option<&str>,
-) -> Vec<Container> {
-    let mut rv = vec![];
-    let mut inter: HashMap<String, Vec<String>> = HashMap::new();
-
-    for container_id in container_ids {
-        let bodies = stream::iter(client.nodes_info())
-            .map(|(hostname, node)| async move {
-                match node
-                    .run_command(
-                        InternalCommand::Ps {
-                            all,
-                            filter: None,
-                            format: None,
-                            last: false,
-                            latests: false,
-                            no_trunc: false,
-                            quiet: false,
-                            size: false,
-                        },
-                        sudo,
-                        identity_file,
-                    )
-                    .await
-                {
-                    Ok(result) => (hostname.clone(), Ok(result)),
-                    Err(e) => (hostname.clone(), Err(e)),
-                }
-            })
-            .buffer_unordered(constants::CONCURRENT_REQUESTS);
+    options: &RunOptions<'_>,
+) -> Lookup {
+    let matchers: Vec<(&str, Matcher)> = container_ids
+        .iter()
+        .map(|query| (*query, Matcher::new(query, options.match_mode)))
+        .collect();
+
+    // A single `ps` sweep of the fleet is enough to look up every container id
+    let (outputs, errors) = ps_sweep(client, all, options).await;
+    let mut rv: Vec<Container> = outputs
+        .iter()
+        .filter_map(|output| node_matches(output, &matchers))
+        .collect();
+    rv.sort_by(|a, b| a.node.cmp(&b.node));
+
+    let mut lookup = match options.match_mode {
+        MatchMode::Exact => split_ambiguous(rv, container_ids),
+        _ => Lookup {
+            containers: rv,
+            ..Default::default()
+        },
+    };
+    lookup.errors = errors;
+    lookup.reached = !outputs.is_empty();
+    lookup.outputs = outputs;
+
+    lookup
+}
+
+/// Returns every container on the nodes of `client` together with the node it runs on, sorted
+/// by node, and the nodes that could not be reached.
+pub async fn list_containers(
+    client: &Client,
+    all: bool,
+    options: &RunOptions<'_>,
+) -> (Vec<(String, ContainerSummary)>, Vec<NodeError>) {
+    let (outputs, errors) = ps_sweep(client, all, options).await;
+
+    (node_containers(&outputs), errors)
+}
+
+/// Returns every container in the `ps` outputs of `outputs` together with the node it runs on,
+/// sorted by node.
+fn node_containers(outputs: &[NodeOutput]) -> Vec<(String, ContainerSummary)> {
+    let mut rv: Vec<(String, ContainerSummary)> = outputs
+        .iter()
+        .flat_map(|output| {
+            parse_json_lines::<ContainerSummary>(&output.stdout)
+                .into_iter()
+                .map(|container| (output.node.clone(), container))
+        })
+        .collect();
+    rv.sort_by(|a, b| a.0.cmp(&b.0));
+
+    rv
+}
 
-        let containers = bodies
-            .collect::<Vec<(String, Result<String, NodeError>)>>()
+/// Runs `ps` on every node, the nodes that could not be reached are returned separately.
+async fn ps_sweep(
+    client: &Client,
+    all: bool,
+    options: &RunOptions<'_>,
+) -> (Vec<NodeOutput>, Vec<NodeError>) {
+    let bodies = stream::iter(client.nodes_info())
+        .map(|(_, node)| async move {
+            node.run_command(
+                client.sessions(),
+                InternalCommand::Ps {
+                    all,
+                    filter: None,
+                    format: None,
+                    last: false,
+                    latests: false,
+                    no_trunc: true,
+                    quiet: false,
+                    size: false,
+                },
+                options,
+            )
             .await
-            .iter()
-            .filter_map(|(hostname, result)| node_filter_map((hostname, result), container_id))
-            .collect::<Vec<(String, String, String)>>();
+        })
+        .buffer_unordered(options.concurrency);
 
-        for container in containers {
-            let hostname = container.0;
-            let container_id = container.2;
+    split_sweep(bodies.collect::<Vec<Result<NodeOutput, NodeError>>>().await)
+}
 
-            if let Some(host_containers) = inter.get_mut(&hostname) {
-                host_containers.push(container_id);
-            } else {
-                inter.insert(hostname, vec![container_id]);
-            }
+/// Splits the results of a `ps` sweep into the outputs of the nodes that could be searched and
+/// the errors of the others. A node where `ps` failed, like when the docker daemon is down, could
+/// not be searched either.
+fn split_sweep(results: Vec<Result<NodeOutput, NodeError>>) -> (Vec<NodeOutput>, Vec<NodeError>) {
+    let mut outputs: Vec<NodeOutput> = vec![];
+    let mut errors: Vec<NodeError> = vec![];
+    for result in results {
+        match result {
+            Ok(output) if !output.success() => errors.push(NodeError::DockerError(
+                output.node,
+                output.stderr.trim_end().to_string(),
+            )),
+            Ok(output) => outputs.push(output),
+            Err(e) => errors.push(e),
         }
     }
 
-    for (hostname, containers) in inter.iter() {
-        if !containers.is_empty() {
-            rv.push(Container::new(
-                hostname.to_string(),
-                hostname.to_string(),
-                containers.to_vec(),
-            ))
+    (outputs, errors)
+}
+
+/// Returns the home directory of the current user.
+pub fn home_dir() -> PathBuf {
+    PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| "/home/root".into()))
+}
+
+/// Expands a leading `~/` in `path` to the home directory.
+pub fn expand_tilde(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => home_dir().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Returns the directory docker-manager reads its own files from, this respects
+/// `$XDG_CONFIG_HOME` and falls back to `~/.config/docker-manager`.
+pub fn config_dir() -> PathBuf {
+    match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join("docker-manager"),
+        _ => home_dir().join(".config").join("docker-manager"),
+    }
+}
+
+/// Returns the containers on the node of `output` that match any of `matchers`, every
+/// container is only returned once.
+fn node_matches(output: &NodeOutput, matchers: &[(&str, Matcher)]) -> Option<Container> {
+    let mut matches: Vec<ContainerMatch> = vec![];
+
+    for container in parse_json_lines::<ContainerSummary>(&output.stdout) {
+        let matched = matchers
+            .iter()
+            .find_map(|(query, matcher)| matcher.matches(&container).map(|rule| (query, rule)));
+
+        if let Some((query, rule)) = matched {
+            matches.push(ContainerMatch {
+                query: query.to_string(),
+                rule,
+                summary: container,
+            });
         }
     }
 
-    rv
+    // like docker, a container id that is the name of a container does not also match the
+    // containers whose id starts with it
+    let names: Vec<String> = matches
+        .iter()
+        .filter(|matched| matched.rule == MatchRule::Name)
+        .map(|matched| matched.query.clone())
+        .collect();
+    matches
+        .retain(|matched| matched.rule != MatchRule::IdPrefix || !names.contains(&matched.query));
+
+    match matches.is_empty() {
+        true => None,
+        false => Some(Container::new(output.node.clone(), matches)),
+    }
 }
 
-/// Returns (hostname, node, container_id)
-fn node_filter_map(
-    hostname_node: (&str, &Result<String, NodeError>),
-    container_id: &str,
-) -> Option<(String, String, String)> {
-    match hostname_node.1 {
-        Ok(s) => {
-            if s.contains(container_id) {
-                Some((
-                    hostname_node.0.to_string(),
-                    String::from(s.split('\n').next().unwrap_or("")),
-                    container_id.to_string(),
-                ))
-            } else {
-                None
-            }
+/// Moves the matches of every container id that only matched by id prefix, but did so more than
+/// once, out of `containers`. Docker refuses such a prefix instead of acting on all of them.
+fn split_ambiguous(containers: Vec<Container>, container_ids: &[&str]) -> Lookup {
+    let matches: Vec<&ContainerMatch> = containers
+        .iter()
+        .flat_map(|container| container.matches())
+        .collect();
+    let ambiguous_ids: Vec<&str> = container_ids
+        .iter()
+        .filter(|query| {
+            let hits: Vec<&&ContainerMatch> = matches
+                .iter()
+                .filter(|matched| matched.query == **query)
+                .collect();
+
+            hits.len() > 1
+                && hits
+                    .iter()
+                    .all(|matched| matched.rule == MatchRule::IdPrefix)
+        })
+        .copied()
+        .collect();
+
+    let mut rv = Lookup::default();
+    for container in containers {
+        let (matches, ambiguous): (Vec<ContainerMatch>, Vec<ContainerMatch>) = container
+            .matches
+            .into_iter()
+            .partition(|matched| !ambiguous_ids.contains(&matched.query.as_str()));
+
+        rv.ambiguous.extend(
+            ambiguous
+                .into_iter()
+                .map(|matched| (container.node.clone(), matched)),
+        );
+        if !matches.is_empty() {
+            rv.containers.push(Container::new(container.node, matches));
         }
-        Err(_) => None,
     }
+
+    rv
 }
 
-#[derive(Debug)]
-pub struct FindContainerResult {
-    containers: Vec<Container>,
+/// The containers `find_containers` found for the container ids from the command line.
+#[derive(Default)]
+pub struct Lookup {
+    /// The matched containers grouped by node, sorted by node
+    pub containers: Vec<Container>,
+    /// `(node, match)` of the container ids that matched more than one container by id prefix,
+    /// these are not part of `containers`
+    pub ambiguous: Vec<(String, ContainerMatch)>,
+    /// The nodes that could not be searched
+    pub errors: Vec<NodeError>,
+    /// Whether at least one node could be searched
+    pub reached: bool,
+    /// The `ps` output of the nodes that could be searched
+    outputs: Vec<NodeOutput>,
 }
 
-impl Iterator for FindContainerResult {
-    type Item = Container;
+impl Lookup {
+    /// Checks if `query` was left out of `containers` because it is ambiguous.
+    pub fn is_ambiguous(&self, query: &str) -> bool {
+        self.ambiguous
+            .iter()
+            .any(|(_, matched)| matched.query == query)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.containers.pop()
+    /// Returns every container on the nodes that were searched, like `list_containers` does.
+    pub fn all_containers(&self) -> Vec<(String, ContainerSummary)> {
+        node_containers(&self.outputs)
+    }
+
+    /// Takes the reasons why container ids could not be looked up out of the lookup: one error
+    /// for every ambiguous container id and one for every node that could not be searched. When
+    /// nothing was found on the nodes that could be searched `container_id` is reported as
+    /// missing, unless it is empty.
+    pub fn take_errors<'a>(&mut self, container_id: Vec<&'a str>) -> Vec<CommandError<'a>> {
+        let ambiguous = std::mem::take(&mut self.ambiguous);
+        let mut queries: Vec<&str> = vec![];
+        for (_, matched) in &ambiguous {
+            if !queries.contains(&matched.query.as_str()) {
+                queries.push(&matched.query);
+            }
+        }
+
+        let mut rv: Vec<CommandError> = queries
+            .into_iter()
+            .map(|query| {
+                CommandError::MutlipleNodesFound(
+                    ambiguous
+                        .iter()
+                        .filter(|(_, matched)| matched.query == query)
+                        .map(|(node, matched)| (node.clone(), matched.summary.clone()))
+                        .collect(),
+                )
+            })
+            .collect();
+
+        if self.containers.is_empty() && rv.is_empty() && self.reached && !container_id.is_empty() {
+            rv.push(CommandError::NoMultipleNodesFound(container_id));
+        }
+        rv.extend(
+            std::mem::take(&mut self.errors)
+                .into_iter()
+                .map(CommandError::NodeError),
+        );
+
+        rv
     }
 }
 
+/// A container that was matched by one of the container ids from the command line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContainerMatch {
+    /// The container id as it was given on the command line
+    pub query: String,
+    pub rule: MatchRule,
+    pub summary: ContainerSummary,
+}
+
 #[derive(Debug)]
 pub struct Container {
-    hostname: String,
     node: String,
-    container_id: Vec<String>,
+    matches: Vec<ContainerMatch>,
 }
 
 impl Container {
-    fn new(hostname: String, node: String, container_id: Vec<String>) -> Self {
-        Self {
-            hostname,
-            node,
-            container_id,
-        }
-    }
-
-    pub fn hostname(&self) -> &str {
-        &self.hostname
+    fn new(node: String, matches: Vec<ContainerMatch>) -> Self {
+        Self { node, matches }
     }
 
     pub fn node(&self) -> &str {
         &self.node
     }
 
+    /// Returns the full ids of the matched containers.
     pub fn id(&self) -> Vec<&str> {
-        let mut rv: Vec<&str> = vec![];
-
-        for container in &self.container_id {
-            rv.push(container);
-        }
+        self.matches
+            .iter()
+            .map(|container| container.summary.id.as_str())
+            .collect()
+    }
 
-        rv
+    pub fn matches(&self) -> &[ContainerMatch] {
+        &self.matches
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{node_filter_map, NodeError};
+    use std::time::Duration;
+
+    use super::{
+        node_matches, split_ambiguous, split_sweep, Container, ContainerMatch, ContainerSummary,
+        Lookup, MatchMode, MatchRule, Matcher, NodeError, NodeOutput,
+    };
+    use crate::utility::{CommandError, ExitCode};
+
+    fn container(id: &str, names: &str) -> ContainerSummary {
+        ContainerSummary {
+            id: id.into(),
+            names: names.into(),
+            image: "nginx-web".into(),
+            ports: "0.0.0.0:80->80/tcp".into(),
+            ..Default::default()
+        }
+    }
 
     #[test]
-    fn test_node_filter_map() {
-        let container_id = String::from("123123123");
-        let original: Vec<(String, Result<String, NodeError>, String)> = vec![
-            (
-                "123123123".into(),
-                Ok("123123123".into()),
-                "123123123".into(),
-            ),
-            (
-                "123123123".into(),
-                Ok("123123123".into()),
-                "123123123".into(),
-            ),
-            (
-                "123123123".into(),
-                Ok("123123123".into()),
-                "123123123".into(),
+    fn test_matcher() {
+        let web = container("3f8a00f137a0", "web");
+        let worker = container("a1b2c3d4e5f6", "web-worker,other");
+
+        let exact = Matcher::new("web", MatchMode::Exact);
+        assert_eq!(Some(MatchRule::Name), exact.matches(&web));
+        assert_eq!(None, exact.matches(&worker));
+
+        let id = Matcher::new("3f8a", MatchMode::Exact);
+        assert_eq!(Some(MatchRule::IdPrefix), id.matches(&web));
+        assert_eq!(None, Matcher::new("", MatchMode::Exact).matches(&web));
+        assert_eq!(None, Matcher::new("80", MatchMode::Exact).matches(&web));
+
+        let prefix = Matcher::new("web", MatchMode::Prefix);
+        assert_eq!(Some(MatchRule::NamePrefix), prefix.matches(&web));
+        assert_eq!(Some(MatchRule::NamePrefix), prefix.matches(&worker));
+
+        let glob = Matcher::new("*-work?r", MatchMode::Glob);
+        assert_eq!(None, glob.matches(&web));
+        assert_eq!(Some(MatchRule::Glob), glob.matches(&worker));
+
+        let regex = Matcher::new("^oth", MatchMode::Regex);
+        assert_eq!(None, regex.matches(&web));
+        assert_eq!(Some(MatchRule::Regex), regex.matches(&worker));
+    }
+
+    #[test]
+    fn test_node_matches() {
+        let stdout = [
+            container("3f8a00f137a0", "web"),
+            container("a1b2c3d4e5f6", "web-worker"),
+        ]
+        .iter()
+        .map(|c| serde_json::to_string(c).unwrap())
+        .collect::<Vec<String>>()
+        .join("\n");
+        let output = NodeOutput {
+            node: "abc".into(),
+            container: None,
+            exit_code: Some(0),
+            stdout,
+            stderr: String::new(),
+            duration: Duration::ZERO,
+        };
+
+        let matchers = vec![
+            ("web", Matcher::new("web", MatchMode::Exact)),
+            ("3f8a", Matcher::new("3f8a", MatchMode::Exact)),
+        ];
+        let container = node_matches(&output, &matchers).unwrap();
+
+        assert_eq!("abc", container.node());
+        assert_eq!(vec!["3f8a00f137a0"], container.id());
+        assert_eq!("web", container.matches()[0].query);
+        assert_eq!(MatchRule::Name, container.matches()[0].rule);
+
+        let matchers = vec![("nginx", Matcher::new("nginx", MatchMode::Exact))];
+        assert!(node_matches(&output, &matchers).is_none());
+    }
+
+    #[test]
+    fn test_node_matches_name_before_id_prefix() {
+        let stdout = [
+            container("db12aa000000", "cache"),
+            container("0f0f0f000000", "db"),
+        ]
+        .iter()
+        .map(|c| serde_json::to_string(c).unwrap())
+        .collect::<Vec<String>>()
+        .join("\n");
+        let output = NodeOutput {
+            node: "abc".into(),
+            container: None,
+            exit_code: Some(0),
+            stdout,
+            stderr: String::new(),
+            duration: Duration::ZERO,
+        };
+
+        let matchers = vec![("db", Matcher::new("db", MatchMode::Exact))];
+        let container = node_matches(&output, &matchers).unwrap();
+
+        assert_eq!(vec!["0f0f0f000000"], container.id());
+        assert_eq!(MatchRule::Name, container.matches()[0].rule);
+    }
+
+    #[test]
+    fn test_split_ambiguous() {
+        let matched = |query: &str, rule, id: &str, name: &str| ContainerMatch {
+            query: query.into(),
+            rule,
+            summary: container(id, name),
+        };
+        let containers = vec![
+            Container::new(
+                "abc".into(),
+                vec![
+                    matched("a", MatchRule::IdPrefix, "a1b2c3d4e5f6", "web"),
+                    matched("db", MatchRule::Name, "0f1e2d3c4b5a", "db"),
+                ],
             ),
-            (
-                "asdjkfhas".into(),
-                Ok("asdjkfhas".into()),
-                "asdjkfhas".into(),
+            Container::new(
+                "def".into(),
+                vec![
+                    matched("a", MatchRule::IdPrefix, "a9b8c7d6e5f4", "worker"),
+                    matched("3f", MatchRule::IdPrefix, "3f8a00f137a0", "cache"),
+                ],
             ),
         ];
 
-        let new = original
-            .into_iter()
-            .filter_map(|(hostname, result, _)| {
-                node_filter_map((&hostname, &result), &container_id)
-            })
-            .collect::<Vec<(String, String, String)>>();
+        let mut lookup = split_ambiguous(containers, &["a", "db", "3f"]);
 
-        let correct: Vec<(String, String, String)> = vec![
-            ("123123123".into(), "123123123".into(), "123123123".into()),
-            ("123123123".into(), "123123123".into(), "123123123".into()),
-            ("123123123".into(), "123123123".into(), "123123123".into()),
-        ];
+        assert!(lookup.is_ambiguous("a"));
+        assert!(!lookup.is_ambiguous("3f"));
+        assert_eq!(2, lookup.containers.len());
+        assert_eq!(vec!["0f1e2d3c4b5a"], lookup.containers[0].id());
+        assert_eq!(vec!["3f8a00f137a0"], lookup.containers[1].id());
+
+        let errors = lookup.take_errors(vec!["a", "db", "3f"]);
+        assert_eq!(1, errors.len());
+        assert_eq!(
+            "Multiple containers found with matching criteria, pick one with --node:\nabc: web\ndef: worker",
+            errors[0].to_string()
+        );
+        assert!(lookup.ambiguous.is_empty());
+    }
+
+    #[test]
+    fn test_split_sweep() {
+        let output = |node: &str, exit_code| NodeOutput {
+            node: node.into(),
+            container: None,
+            exit_code: Some(exit_code),
+            stdout: String::new(),
+            stderr: match exit_code {
+                0 => String::new(),
+                _ => {
+                    "permission denied while trying to connect to the Docker daemon socket\n".into()
+                }
+            },
+            duration: Duration::ZERO,
+        };
 
-        assert_eq!(new, correct);
+        let (outputs, errors) = split_sweep(vec![
+            Ok(output("abc", 0)),
+            Ok(output("def", 1)),
+            Err(NodeError::Timeout("ghi".into(), Duration::from_secs(1))),
+        ]);
+
+        assert_eq!(1, outputs.len());
+        assert_eq!("abc", outputs[0].node);
+        assert_eq!(2, errors.len());
+        assert_eq!(
+            "[NodeError] def: permission denied while trying to connect to the Docker daemon socket",
+            errors[0].to_string()
+        );
+    }
+
+    #[test]
+    fn test_lookup_unreachable() {
+        let unreachable = || Lookup {
+            errors: vec![
+                NodeError::Timeout("abc".into(), Duration::from_secs(1)),
+                NodeError::Timeout("def".into(), Duration::from_secs(1)),
+            ],
+            ..Default::default()
+        };
+
+        // nothing can be missing when no node could be searched
+        let results: Vec<Result<NodeOutput, CommandError>> = unreachable()
+            .take_errors(vec!["web"])
+            .into_iter()
+            .map(Err)
+            .collect();
+        assert_eq!(2, results.len());
+        assert_eq!(ExitCode::Connection, ExitCode::from_results(&results));
+
+        let mut lookup = Lookup {
+            reached: true,
+            ..unreachable()
+        };
+        let results: Vec<Result<NodeOutput, CommandError>> = lookup
+            .take_errors(vec!["web"])
+            .into_iter()
+            .map(Err)
+            .collect();
+        assert!(matches!(
+            results[0],
+            Err(CommandError::NoMultipleNodesFound(_))
+        ));
+        assert_eq!(ExitCode::NoMatch, ExitCode::from_results(&results));
     }
 }
@@ -1,25 +1,31 @@
-use crate::constants;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::io::IsTerminal;
+use std::time::Duration;
+
 use futures::{stream, StreamExt};
 
-use crate::cli::InternalCommand;
-use crate::client::{Client, Node, NodeError};
-use crate::utility::find_containers;
+use crate::cli::{InternalCommand, RunArgs, System, SystemCommand};
+use crate::client::{Client, Node, NodeError, NodeSelector, RunOptions};
+use tokio::sync::mpsc;
+
+use crate::formatter::{
+    parse_json_lines, pick_container, ContainerSummary, InfoSummary, LogPrinter, LogSink,
+    JSON_FORMAT,
+};
+use crate::utility::{find_containers, list_containers, NodeOutput};
 
-use super::other::Container;
+use super::other::{ContainerMatch, Lookup};
 
 pub async fn run_command<'a>(
     command: InternalCommand<'a>,
-    sudo: bool,
-    regex: Option<&str>,
-    identity_file: Option<&str>,
-) -> Vec<Result<String, CommandError<'a>>> {
-    let config_path = format!(
-        "{}/.ssh/config",
-        std::env::var("HOME").unwrap_or_else(|_| "/home/root".into())
-    );
-    let client = Client::from_config(config_path, regex);
+    selector: &NodeSelector<'_>,
+    options: &RunOptions<'_>,
+) -> Vec<Result<NodeOutput, CommandError<'a>>> {
+    let client = &Client::new(selector);
 
     match command {
+        InternalCommand::Create(args) => run_placed(client, args, true, false, options).await,
         InternalCommand::Exec {
             container_id,
             command,
@@ -29,51 +35,62 @@ pub async fn run_command<'a>(
             env_file,
             interactive,
             privileged,
+            tty,
             user,
             workdir,
+            node,
+            all_matches,
         } => {
-            let node_containers: Vec<Container> =
-                find_containers(client, &[container_id], sudo, false, identity_file).await;
+            // there is exactly one target unless `all_matches` is set
+            let (targets, errors) =
+                find_targets(client, container_id, node, all_matches, options).await;
+            if targets.is_empty() {
+                return errors.into_iter().map(Err).collect();
+            }
 
-            match node_containers.len() {
-                0 => {
-                    vec![Err(CommandError::NoNodesFound(container_id))]
-                }
-                1 => {
-                    // unwrap is safe here since we .unwrap()check if there is exactly 1 element
-                    let node_tuple = node_containers.get(0).unwrap().to_owned();
-                    let node = Node::new(node_tuple.hostname().to_string());
-                    match node
-                        .run_command(
-                            InternalCommand::Exec {
-                                container_id,
-                                command,
-                                detach,
-                                detach_keys,
-                                env,
-                                env_file,
-                                interactive,
-                                privileged,
-                                user,
-                                workdir,
-                            },
-                            sudo,
-                            identity_file,
-                        )
-                        .await
-                    {
-                        Ok(s) => vec![Ok(s)],
-                        Err(e) => vec![Err(CommandError::NodeError(e))],
+            let results =
+                run_on_targets(client, &targets, options, |target| InternalCommand::Exec {
+                    container_id: &target.id,
+                    command: command.clone(),
+                    detach,
+                    detach_keys,
+                    env: env.clone(),
+                    env_file: env_file.clone(),
+                    interactive,
+                    privileged,
+                    tty,
+                    user,
+                    workdir,
+                    node: None,
+                    all_matches: false,
+                })
+                .await;
+
+            errors.into_iter().map(Err).chain(results).collect()
+        }
+        InternalCommand::HostExec { command } => {
+            let bodies = stream::iter(client.nodes_info())
+                .map(|(_, node)| {
+                    let command = command.clone();
+
+                    async move {
+                        match node
+                            .run_command(
+                                client.sessions(),
+                                InternalCommand::HostExec { command },
+                                options,
+                            )
+                            .await
+                        {
+                            Ok(result) => Ok(result),
+                            Err(e) => Err(CommandError::NodeError(e)),
+                        }
                     }
-                }
-                _ => {
-                    let nodes = node_containers
-                        .iter()
-                        .map(|result| result.id().join(" "))
-                        .collect::<Vec<String>>();
-                    vec![Err(CommandError::MutlipleNodesFound(nodes))]
-                }
-            }
+                })
+                .buffer_unordered(options.concurrency);
+            bodies
+                .collect::<Vec<Result<NodeOutput, CommandError>>>()
+                .await
         }
         InternalCommand::Images {
             all,
@@ -87,6 +104,7 @@ pub async fn run_command<'a>(
                 .map(|(_, node)| async {
                     match node
                         .run_command(
+                            client.sessions(),
                             InternalCommand::Images {
                                 all,
                                 digest,
@@ -95,8 +113,7 @@ pub async fn run_command<'a>(
                                 no_trunc,
                                 quiet,
                             },
-                            sudo,
-                            identity_file,
+                            options,
                         )
                         .await
                     {
@@ -104,8 +121,98 @@ pub async fn run_command<'a>(
                         Err(e) => Err(CommandError::NodeError(e)),
                     }
                 })
-                .buffer_unordered(constants::CONCURRENT_REQUESTS);
-            bodies.collect::<Vec<Result<String, CommandError>>>().await
+                .buffer_unordered(options.concurrency);
+            bodies
+                .collect::<Vec<Result<NodeOutput, CommandError>>>()
+                .await
+        }
+        InternalCommand::Inspect {
+            container_id,
+            format,
+            size,
+        } => {
+            let mut node_containers = find_containers(client, &container_id, true, options).await;
+
+            // Anything that is not a container, like an image, could be on any node
+            let others: Vec<&str> = container_id
+                .iter()
+                .filter(|query| {
+                    !node_containers.is_ambiguous(query)
+                        && !node_containers.containers.iter().any(|container| {
+                            container
+                                .matches()
+                                .iter()
+                                .any(|matched| matched.query == **query)
+                        })
+                })
+                .copied()
+                .collect();
+            let errors = node_containers.take_errors(vec![]);
+
+            // `(node, ids, lookup)`, lookups are allowed to not find anything on a node
+            let mut jobs: Vec<(&Node, Vec<&str>, bool)> = node_containers
+                .containers
+                .iter()
+                .map(|container| (found_on(client, container.node()), container.id(), false))
+                .collect();
+            for query in &others {
+                for (_, node) in client.nodes_info() {
+                    jobs.push((node, vec![*query], true));
+                }
+            }
+
+            let results = run_in_order(client, &jobs, options, |(node, ids, _)| {
+                (
+                    *node,
+                    InternalCommand::Inspect {
+                        container_id: ids.clone(),
+                        format,
+                        size,
+                    },
+                )
+            })
+            .await;
+
+            let mut found: Vec<&str> = vec![];
+            let mut rv: Vec<Result<NodeOutput, CommandError>> =
+                errors.into_iter().map(Err).collect();
+
+            for ((_, ids, lookup), result) in jobs.iter().zip(results) {
+                match result {
+                    Ok(output) if *lookup && is_missing(&output) => (),
+                    Ok(output) => {
+                        if *lookup {
+                            found.extend(ids);
+                        }
+                        rv.push(Ok(output));
+                    }
+                    Err(e) => rv.push(Err(e)),
+                }
+            }
+
+            let missing: Vec<&str> = others
+                .into_iter()
+                .filter(|query| !found.contains(query))
+                .collect();
+            if !missing.is_empty() {
+                rv.push(Err(CommandError::NoMultipleNodesFound(missing)));
+            }
+
+            rv
+        }
+        InternalCommand::Kill {
+            container_id,
+            signal,
+        } => {
+            let mut lookup = find_containers(client, &container_id, false, options).await;
+
+            run_on_containers(client, container_id, &mut lookup, options, |ids| {
+                InternalCommand::Kill {
+                    container_id: ids,
+                    signal,
+                }
+            })
+            .await
         }
         InternalCommand::Logs {
             container_id,
@@ -115,45 +222,100 @@ pub async fn run_command<'a>(
             tail,
             timestamps,
             until,
+            node,
+            sink: _,
         } => {
-            let node_containers: Vec<Container> =
-                find_containers(client, &[container_id], sudo, false, identity_file).await;
-            match node_containers.len() {
-                0 => {
-                    vec![Err(CommandError::NoNodesFound(container_id))]
-                }
-                1 => {
-                    // unwrap is safe here since we check if there is exactly 1 element
-                    let node_tuple = node_containers.get(0).unwrap().to_owned();
-                    let node = Node::new(node_tuple.hostname().to_string());
-                    match node
-                        .run_command(
-                            InternalCommand::Logs {
-                                container_id,
-                                details,
-                                follow,
-                                since,
-                                tail,
-                                timestamps,
-                                until,
-                            },
-                            sudo,
-                            identity_file,
-                        )
-                        .await
-                    {
-                        Ok(s) => vec![Ok(s)],
-                        Err(e) => vec![Err(CommandError::NodeError(e))],
-                    }
-                }
-                _ => {
-                    let nodes = node_containers
-                        .iter()
-                        .map(|result| result.id().join(" "))
-                        .collect::<Vec<String>>();
-                    vec![Err(CommandError::MutlipleNodesFound(nodes))]
-                }
+            let (targets, errors) = match container_id {
+                Some(container_id) => find_targets(client, container_id, node, true, options).await,
+                None => all_targets(client, node, options).await,
+            };
+            if targets.is_empty() {
+                return errors.into_iter().map(Err).collect();
             }
+
+            let sources: Vec<(&str, &str)> = targets
+                .iter()
+                .map(|target| (target.node.address(), target.name.as_str()))
+                .collect();
+            let printer = LogPrinter::new(&sources, std::io::stdout().is_terminal(), timestamps);
+            let (tx, rx) = mpsc::unbounded_channel();
+
+            // Followed logs never finish, so every container needs to be streamed at once
+            let concurrency = match follow {
+                true => targets.len(),
+                false => options.concurrency,
+            };
+
+            let targets = &targets;
+            let running = async move {
+                let bodies = stream::iter(targets.iter().enumerate())
+                    .map(|(source, target)| {
+                        let sink = LogSink {
+                            tx: tx.clone(),
+                            source,
+                        };
+
+                        async move {
+                            match target
+                                .node
+                                .run_command(
+                                    client.sessions(),
+                                    InternalCommand::Logs {
+                                        container_id: Some(&target.id),
+                                        details,
+                                        follow,
+                                        since,
+                                        tail,
+                                        timestamps,
+                                        until,
+                                        node: None,
+                                        sink: Some(&sink),
+                                    },
+                                    options,
+                                )
+                                .await
+                            {
+                                Ok(output) => Ok(NodeOutput {
+                                    container: Some(target.name.clone()),
+                                    ..output
+                                }),
+                                Err(e) => Err(CommandError::NodeError(e)),
+                            }
+                        }
+                    })
+                    .buffer_unordered(concurrency);
+
+                let results = bodies
+                    .collect::<Vec<Result<NodeOutput, CommandError>>>()
+                    .await;
+                // the printer stops once the last sender is gone
+                drop(tx);
+
+                results
+            };
+
+            let (mut results, _) = tokio::join!(running, printer.print_stream(rx));
+            results.extend(errors.into_iter().map(Err));
+            // keep the order of the prefixes when the captured logs are printed
+            results.sort_by_key(|result| match result {
+                Ok(output) => sources
+                    .iter()
+                    .position(|(node, container)| {
+                        *node == output.node && Some(*container) == output.container.as_deref()
+                    })
+                    .unwrap_or_default(),
+                Err(_) => usize::MAX,
+            });
+
+            results
+        }
+        InternalCommand::Pause { container_id } => {
+            let mut lookup = find_containers(client, &container_id, false, options).await;
+
+            run_on_containers(client, container_id, &mut lookup, options, |ids| {
+                InternalCommand::Pause { container_id: ids }
+            })
+            .await
         }
         InternalCommand::Ps {
             all,
@@ -169,6 +331,7 @@ pub async fn run_command<'a>(
                 .map(|(_, node)| async {
                     match node
                         .run_command(
+                            client.sessions(),
                             InternalCommand::Ps {
                                 all,
                                 filter,
@@ -179,8 +342,7 @@ pub async fn run_command<'a>(
                                 quiet,
                                 size,
                             },
-                            sudo,
-                            identity_file,
+                            options,
                         )
                         .await
                     {
@@ -188,204 +350,88 @@ pub async fn run_command<'a>(
                         Err(e) => Err(CommandError::NodeError(e)),
                     }
                 })
-                .buffer_unordered(constants::CONCURRENT_REQUESTS);
-            bodies.collect::<Vec<Result<String, CommandError>>>().await
+                .buffer_unordered(options.concurrency);
+            bodies
+                .collect::<Vec<Result<NodeOutput, CommandError>>>()
+                .await
         }
         InternalCommand::Restart { time, container_id } => {
-            let node_containers: Vec<Container> =
-                find_containers(client, &container_id, sudo, true, identity_file).await;
-
-            match node_containers.len() {
-                0 => {
-                    vec![Err(CommandError::NoMultipleNodesFound(container_id))]
-                }
-                _ => {
-                    let bodies = stream::iter(node_containers)
-                        .map(|container| async move {
-                            let node = Node::new(container.node().to_string());
-                            match node
-                                .run_command(
-                                    InternalCommand::Restart {
-                                        time,
-                                        container_id: container.id(),
-                                    },
-                                    sudo,
-                                    identity_file,
-                                )
-                                .await
-                            {
-                                Ok(result) => (container.hostname().to_string(), Ok(result)),
-                                Err(e) => (container.hostname().to_string(), Err(e)),
-                            }
-                        })
-                        .buffer_unordered(constants::CONCURRENT_REQUESTS);
-
-                    let _rv = bodies
-                        .collect::<Vec<(String, Result<String, NodeError>)>>()
-                        .await;
-
-                    let mut rv = vec![];
+            let mut lookup = find_containers(client, &container_id, true, options).await;
 
-                    for (_, res) in _rv {
-                        match res {
-                            Ok(s) => rv.push(Ok(s)),
-                            Err(e) => rv.push(Err(CommandError::NodeError(e))),
-                        }
-                    }
-                    rv
+            run_on_containers(client, container_id, &mut lookup, options, |ids| {
+                InternalCommand::Restart {
+                    time,
+                    container_id: ids,
                 }
-            }
+            })
+            .await
         }
         InternalCommand::Rm {
             container_id,
             force,
             volumes,
         } => {
-            let node_containers: Vec<Container> =
-                find_containers(client, &container_id, sudo, true, identity_file).await;
+            let mut lookup = find_containers(client, &container_id, true, options).await;
 
-            match node_containers.len() {
-                0 => {
-                    vec![Err(CommandError::NoMultipleNodesFound(container_id))]
+            run_on_containers(client, container_id, &mut lookup, options, |ids| {
+                InternalCommand::Rm {
+                    container_id: ids,
+                    force,
+                    volumes,
                 }
-                _ => {
-                    let bodies = stream::iter(node_containers)
-                        .map(|container| async move {
-                            let node = Node::new(container.node().to_string());
-                            match node
-                                .run_command(
-                                    InternalCommand::Rm {
-                                        container_id: container.id(),
-                                        force,
-                                        volumes,
-                                    },
-                                    sudo,
-                                    identity_file,
-                                )
-                                .await
-                            {
-                                Ok(result) => (container.hostname().to_string(), Ok(result)),
-                                Err(e) => (container.hostname().to_string(), Err(e)),
-                            }
-                        })
-                        .buffer_unordered(constants::CONCURRENT_REQUESTS);
-
-                    let _rv = bodies
-                        .collect::<Vec<(String, Result<String, NodeError>)>>()
-                        .await;
-
-                    let mut rv = vec![];
-
-                    for (_, res) in _rv {
-                        match res {
-                            Ok(s) => rv.push(Ok(s)),
-                            Err(e) => rv.push(Err(CommandError::NodeError(e))),
-                        }
-                    }
-                    rv
-                }
-            }
+            })
+            .await
+        }
+        InternalCommand::Run { args, detach } => {
+            run_placed(client, args, false, detach, options).await
         }
         InternalCommand::Start {
             container_id,
             attach,
         } => {
-            let node_containers: Vec<Container> =
-                find_containers(client, &container_id, sudo, true, identity_file).await;
+            let mut lookup = find_containers(client, &container_id, true, options).await;
 
-            match node_containers.len() {
-                0 => {
-                    vec![Err(CommandError::NoMultipleNodesFound(container_id))]
+            run_on_containers(client, container_id, &mut lookup, options, |ids| {
+                InternalCommand::Start {
+                    container_id: ids,
+                    attach,
                 }
-                _ => {
-                    let bodies = stream::iter(node_containers)
-                        .map(|container| async move {
-                            let node = Node::new(container.node().to_string());
-                            match node
-                                .run_command(
-                                    InternalCommand::Start {
-                                        container_id: container.id(),
-                                        attach,
-                                    },
-                                    sudo,
-                                    identity_file,
-                                )
-                                .await
-                            {
-                                Ok(result) => (container.hostname().to_string(), Ok(result)),
-                                Err(e) => (container.hostname().to_string(), Err(e)),
-                            }
-                        })
-                        .buffer_unordered(constants::CONCURRENT_REQUESTS);
-
-                    let _rv = bodies
-                        .collect::<Vec<(String, Result<String, NodeError>)>>()
-                        .await;
-
-                    let mut rv = vec![];
+            })
+            .await
+        }
+        InternalCommand::Stats {
+            container_id,
+            all,
+            no_trunc,
+        } => {
+            let mut lookup = match container_id.is_empty() {
+                true => Lookup::default(),
+                false => find_containers(client, &container_id, all, options).await,
+            };
+            let (jobs, errors) = stats_jobs(client, container_id, &mut lookup);
 
-                    for (_, res) in _rv {
-                        match res {
-                            Ok(s) => rv.push(Ok(s)),
-                            Err(e) => rv.push(Err(CommandError::NodeError(e))),
-                        }
-                    }
-                    rv
-                }
-            }
+            errors
+                .into_iter()
+                .map(Err)
+                .chain(run_stats(client, &jobs, all, no_trunc, options).await)
+                .collect()
         }
         InternalCommand::Stop { container_id } => {
-            let node_containers: Vec<Container> =
-                find_containers(client, &container_id, sudo, false, identity_file).await;
+            let mut lookup = find_containers(client, &container_id, false, options).await;
 
-            match node_containers.len() {
-                0 => {
-                    vec![Err(CommandError::NoMultipleNodesFound(container_id))]
-                }
-                _ => {
-                    let bodies = stream::iter(node_containers)
-                        .map(|container| async move {
-                            let node = Node::new(container.node().to_string());
-                            match node
-                                .run_command(
-                                    InternalCommand::Stop {
-                                        container_id: container.id(),
-                                    },
-                                    sudo,
-                                    identity_file,
-                                )
-                                .await
-                            {
-                                Ok(result) => (container.hostname().to_string(), Ok(result)),
-                                Err(e) => (container.hostname().to_string(), Err(e)),
-                            }
-                        })
-                        .buffer_unordered(constants::CONCURRENT_REQUESTS);
-
-                    let _rv = bodies
-                        .collect::<Vec<(String, Result<String, NodeError>)>>()
-                        .await;
-
-                    let mut rv = vec![];
-
-                    for (_, res) in _rv {
-                        match res {
-                            Ok(s) => rv.push(Ok(s)),
-                            Err(e) => rv.push(Err(CommandError::NodeError(e))),
-                        }
-                    }
-                    rv
-                }
-            }
+            run_on_containers(client, container_id, &mut lookup, options, |ids| {
+                InternalCommand::Stop { container_id: ids }
+            })
+            .await
         }
         InternalCommand::System(command) => {
             let bodies = stream::iter(client.nodes_info())
                 .map(|(_, node)| async {
                     match node
                         .run_command(
+                            client.sessions(),
                             InternalCommand::System(command.clone()),
-                            sudo,
-                            identity_file,
+                            options,
                         )
                         .await
                     {
@@ -393,19 +439,569 @@ pub async fn run_command<'a>(
                         Err(e) => Err(CommandError::NodeError(e)),
                     }
                 })
-                .buffer_unordered(constants::CONCURRENT_REQUESTS);
-            bodies.collect::<Vec<Result<String, CommandError>>>().await
+                .buffer_unordered(options.concurrency);
+            bodies
+                .collect::<Vec<Result<NodeOutput, CommandError>>>()
+                .await
+        }
+        InternalCommand::Top {
+            container_id,
+            options: ps_options,
+        } => {
+            let mut node_containers = find_containers(client, &container_id, false, options).await;
+            let errors = node_containers.take_errors(container_id);
+            if node_containers.containers.is_empty() {
+                return errors.into_iter().map(Err).collect();
+            }
+
+            let targets: Vec<Target> = node_containers
+                .containers
+                .iter()
+                .flat_map(|container| {
+                    container.matches().iter().map(|matched| Target {
+                        node: found_on(client, container.node()),
+                        id: matched.summary.id.clone(),
+                        name: matched.summary.name().to_string(),
+                    })
+                })
+                .collect();
+
+            let results =
+                run_on_targets(client, &targets, options, |target| InternalCommand::Top {
+                    container_id: vec![&target.id],
+                    options: ps_options.clone(),
+                })
+                .await;
+
+            errors.into_iter().map(Err).chain(results).collect()
+        }
+        InternalCommand::Unpause { container_id } => {
+            let mut lookup = find_containers(client, &container_id, false, options).await;
+
+            run_on_containers(client, container_id, &mut lookup, options, |ids| {
+                InternalCommand::Unpause { container_id: ids }
+            })
+            .await
+        }
+    }
+}
+
+/// Runs the command `build` returns for the ids of the containers in `lookup` on every node they
+/// were found on, this is shared by the commands that act on already existing containers like
+/// `stop`. The containers that were found are acted on even if other container ids are ambiguous.
+async fn run_on_containers<'a, 'c, F>(
+    client: &'c Client,
+    container_id: Vec<&'a str>,
+    lookup: &'c mut Lookup,
+    options: &RunOptions<'_>,
+    build: F,
+) -> Vec<Result<NodeOutput, CommandError<'a>>>
+where
+    F: Fn(Vec<&'c str>) -> InternalCommand<'c>,
+{
+    let mut rv: Vec<Result<NodeOutput, CommandError>> = lookup
+        .take_errors(container_id)
+        .into_iter()
+        .map(Err)
+        .collect();
+    let lookup: &'c Lookup = lookup;
+
+    if lookup.containers.is_empty() {
+        return rv;
+    }
+
+    rv.extend(
+        run_in_order(client, &lookup.containers, options, |container| {
+            (found_on(client, container.node()), build(container.id()))
+        })
+        .await,
+    );
+
+    rv
+}
+
+/// Runs the command `build` returns for every target and labels the output with the name of its
+/// container. The results keep the order of `targets`.
+async fn run_on_targets<'a, 'c, F>(
+    client: &Client,
+    targets: &'c [Target<'c>],
+    options: &RunOptions<'_>,
+    build: F,
+) -> Vec<Result<NodeOutput, CommandError<'a>>>
+where
+    F: Fn(&'c Target<'c>) -> InternalCommand<'c>,
+{
+    run_in_order(client, targets, options, |target| {
+        (target.node, build(target))
+    })
+    .await
+    .into_iter()
+    .zip(targets)
+    .map(|(result, target)| {
+        result.map(|output| NodeOutput {
+            container: Some(target.name.clone()),
+            ..output
+        })
+    })
+    .collect()
+}
+
+/// Runs the command `build` returns for every job on the node it is returned with, at most
+/// `options.concurrency` at a time. The results keep the order of `jobs`, so the output does not
+/// depend on which node answers first.
+async fn run_in_order<'a, 'c, J, F>(
+    client: &Client,
+    jobs: &'c [J],
+    options: &RunOptions<'_>,
+    build: F,
+) -> Vec<Result<NodeOutput, CommandError<'a>>>
+where
+    F: Fn(&'c J) -> (&'c Node, InternalCommand<'c>),
+{
+    let build = &build;
+    let bodies = stream::iter(jobs.iter().enumerate())
+        .map(|(index, job)| async move {
+            let (node, command) = build(job);
+            let result = node
+                .run_command(client.sessions(), command, options)
+                .await
+                .map_err(CommandError::NodeError);
+
+            (index, result)
+        })
+        .buffer_unordered(options.concurrency);
+
+    let mut results = bodies.collect::<Vec<_>>().await;
+    results.sort_by_key(|(index, _)| *index);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Returns the node a container was found on, which always exists since it was found on `client`.
+fn found_on<'c>(client: &'c Client, node: &str) -> &'c Node {
+    client
+        .node(node)
+        .expect("containers are only found on the nodes of the client")
+}
+
+/// Runs `stats` every `period` and hands the results of every sweep to `show`, together with the
+/// errors of looking up the containers. The containers are looked up once, before the first
+/// sweep, this only returns when none of them could be found.
+pub async fn watch_stats<'a>(
+    container_id: Vec<&'a str>,
+    all: bool,
+    no_trunc: bool,
+    selector: &NodeSelector<'_>,
+    options: &RunOptions<'_>,
+    period: Duration,
+    mut show: impl FnMut(&[CommandError<'a>], &[Result<NodeOutput, CommandError<'a>>]),
+) -> Vec<Result<NodeOutput, CommandError<'a>>> {
+    let client = &Client::new(selector);
+    let mut lookup = match container_id.is_empty() {
+        true => Lookup::default(),
+        false => find_containers(client, &container_id, all, options).await,
+    };
+    let (jobs, errors) = stats_jobs(client, container_id, &mut lookup);
+    if jobs.is_empty() {
+        return errors.into_iter().map(Err).collect();
+    }
+
+    let mut interval = tokio::time::interval(period);
+    loop {
+        // nodes that can not be reached would otherwise be retried right away
+        interval.tick().await;
+        let results = run_stats(client, &jobs, all, no_trunc, options).await;
+        show(&errors, &results);
+    }
+}
+
+/// Finds the `(node, ids)` pairs `stats` runs on, every container of a node is shown when there
+/// are no ids. The errors tell which containers could not be found.
+fn stats_jobs<'a, 'c>(
+    client: &'c Client,
+    container_id: Vec<&'a str>,
+    lookup: &'c mut Lookup,
+) -> (Vec<(&'c Node, Vec<&'c str>)>, Vec<CommandError<'a>>) {
+    let errors = lookup.take_errors(container_id.clone());
+    let lookup: &'c Lookup = lookup;
+
+    let jobs = match container_id.is_empty() {
+        true => client
+            .nodes_info()
+            .into_iter()
+            .map(|(_, node)| (node, vec![]))
+            .collect(),
+        false => lookup
+            .containers
+            .iter()
+            .map(|container| (found_on(client, container.node()), container.id()))
+            .collect(),
+    };
+
+    (jobs, errors)
+}
+
+/// Runs `stats` for every `(node, ids)` pair. The results keep the order of `jobs` so the nodes
+/// do not move around the table between refreshes.
+async fn run_stats<'a, 'c>(
+    client: &Client,
+    jobs: &'c [(&'c Node, Vec<&'c str>)],
+    all: bool,
+    no_trunc: bool,
+    options: &RunOptions<'_>,
+) -> Vec<Result<NodeOutput, CommandError<'a>>> {
+    run_in_order(client, jobs, options, |(node, ids)| {
+        (
+            *node,
+            InternalCommand::Stats {
+                container_id: ids.clone(),
+                all,
+                no_trunc,
+            },
+        )
+    })
+    .await
+}
+
+/// Creates a container on every node `place` picks, and runs it unless `create` is set.
+async fn run_placed<'a>(
+    client: &Client,
+    args: &'a RunArgs,
+    create: bool,
+    detach: bool,
+    options: &RunOptions<'_>,
+) -> Vec<Result<NodeOutput, CommandError<'a>>> {
+    let nodes = match place(client, args, options).await {
+        Ok(nodes) => nodes,
+        Err(e) => return vec![Err(e)],
+    };
+
+    let bodies = stream::iter(nodes)
+        .map(|node| async move {
+            let command = match create {
+                true => InternalCommand::Create(args),
+                false => InternalCommand::Run { args, detach },
+            };
+
+            match node.run_command(client.sessions(), command, options).await {
+                Ok(result) => Ok(result),
+                Err(e) => Err(CommandError::NodeError(e)),
+            }
+        })
+        .buffer_unordered(options.concurrency);
+    bodies
+        .collect::<Vec<Result<NodeOutput, CommandError>>>()
+        .await
+}
+
+/// Picks the nodes a new container is created on. `--node` is used as is, otherwise the
+/// `--replicas` least loaded nodes according to `docker info` are picked from the selected nodes,
+/// or from the nodes in `--on-group`.
+async fn place<'a, 'c>(
+    client: &'c Client,
+    args: &'a RunArgs,
+    options: &RunOptions<'_>,
+) -> Result<Vec<&'c Node>, CommandError<'a>> {
+    if let Some(address) = &args.node {
+        return match client.node(address) {
+            Some(node) => Ok(vec![node]),
+            None => Err(CommandError::UnknownNode(address)),
+        };
+    }
+
+    let replicas = args.replicas.unwrap_or(1) as usize;
+    let candidates: Vec<&Node> = client
+        .nodes_info()
+        .into_iter()
+        .map(|(_, node)| node)
+        .filter(|node| {
+            args.on_group
+                .as_ref()
+                .is_none_or(|group| node.groups().contains(group))
+        })
+        .collect();
+    if let (Some(group), true) = (&args.on_group, candidates.is_empty()) {
+        return Err(CommandError::EmptyGroup(group));
+    }
+
+    let bodies = stream::iter(candidates)
+        .map(|node| async move {
+            let info = InternalCommand::System(System {
+                command: SystemCommand::Info {
+                    format: Some(JSON_FORMAT.to_string()),
+                },
+            });
+
+            // nodes that can not be reached can not run the container either
+            match node.run_command(client.sessions(), info, options).await {
+                Ok(output) if output.success() => parse_json_lines::<InfoSummary>(&output.stdout)
+                    .into_iter()
+                    .next()
+                    .map(|info| (node, info)),
+                _ => None,
+            }
+        })
+        .buffer_unordered(options.concurrency);
+
+    let mut loads: Vec<(&Node, InfoSummary)> = bodies
+        .collect::<Vec<Option<(&Node, InfoSummary)>>>()
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+    loads.sort_by(|(a, a_info), (b, b_info)| {
+        load_order((a.address(), a_info), (b.address(), b_info))
+    });
+
+    if loads.len() < replicas {
+        return Err(CommandError::NotEnoughNodes(replicas, loads.len()));
+    }
+
+    Ok(loads
+        .into_iter()
+        .take(replicas)
+        .map(|(node, _)| node)
+        .collect())
+}
+
+/// Orders `(address, info)` pairs from the least to the most loaded node. Nodes that run fewer
+/// containers come first, then nodes with more CPUs and memory. Nodes that are equally loaded are
+/// ordered by address, so the same nodes are picked every time.
+fn load_order((a, a_info): (&str, &InfoSummary), (b, b_info): (&str, &InfoSummary)) -> Ordering {
+    a_info
+        .containers_running
+        .cmp(&b_info.containers_running)
+        .then_with(|| b_info.ncpu.cmp(&a_info.ncpu))
+        .then_with(|| b_info.mem_total.cmp(&a_info.mem_total))
+        .then_with(|| a.cmp(b))
+}
+
+/// A container a command runs on.
+struct Target<'c> {
+    node: &'c Node,
+    /// The full id of the container, or the container as it was given with `--node`
+    id: String,
+    name: String,
+}
+
+/// Finds the containers a command like `exec` or `logs` runs on. Unless `many` is set this has
+/// to be exactly one container. When `node` is given the container is not looked up and passed
+/// to docker as is. The nodes that could not be searched are returned as errors, when no
+/// container is returned the errors tell why.
+async fn find_targets<'a, 'c>(
+    client: &'c Client,
+    container_id: &'a str,
+    node: Option<&'a str>,
+    many: bool,
+    options: &RunOptions<'_>,
+) -> (Vec<Target<'c>>, Vec<CommandError<'a>>) {
+    if let Some(address) = node {
+        return match client.node(address) {
+            Some(node) => (
+                vec![Target {
+                    node,
+                    id: container_id.to_string(),
+                    name: container_id.to_string(),
+                }],
+                vec![],
+            ),
+            None => (vec![], vec![CommandError::UnknownNode(address)]),
+        };
+    }
+
+    // an ambiguous id prefix is resolved here, together with any other duplicates
+    let mut node_containers = find_containers(client, &[container_id], false, options).await;
+    let mut errors: Vec<CommandError> = std::mem::take(&mut node_containers.errors)
+        .into_iter()
+        .map(CommandError::NodeError)
+        .collect();
+    let matches: Vec<(&str, &ContainerMatch)> = node_containers
+        .containers
+        .iter()
+        .flat_map(|container| {
+            container
+                .matches()
+                .iter()
+                .map(move |matched| (container.node(), matched))
+        })
+        .chain(
+            node_containers
+                .ambiguous
+                .iter()
+                .map(|(node, matched)| (node.as_str(), matched)),
+        )
+        .collect();
+
+    if matches.len() == 1 || (many && !matches.is_empty()) {
+        let targets = matches
+            .iter()
+            .map(|(node, matched)| Target {
+                node: found_on(client, node),
+                id: matched.summary.id.clone(),
+                name: matched.summary.name().to_string(),
+            })
+            .collect();
+
+        return (targets, errors);
+    }
+
+    // none of the nodes could be searched, so there is nothing to pick from either
+    if !node_containers.reached {
+        return (vec![], errors);
+    }
+
+    let error = match matches.is_empty() {
+        true => CommandError::NoNodesFound(container_id),
+        false => CommandError::MutlipleNodesFound(
+            matches
+                .iter()
+                .map(|(node, matched)| (node.to_string(), matched.summary.clone()))
+                .collect(),
+        ),
+    };
+
+    if !options.pick {
+        errors.insert(0, error);
+        return (vec![], errors);
+    }
+
+    // Without any match every container is a candidate
+    let candidates: Vec<(String, ContainerSummary)> = match matches.is_empty() {
+        true => node_containers.all_containers(),
+        false => matches
+            .iter()
+            .map(|(node, matched)| (node.to_string(), matched.summary.clone()))
+            .collect(),
+    };
+    let rows: Vec<(&str, &ContainerSummary)> = candidates
+        .iter()
+        .map(|(node, container)| (node.as_str(), container))
+        .collect();
+
+    match pick_container(&format!("Pick a container for {}", container_id), &rows) {
+        Some(index) => {
+            let (node, container) = &candidates[index];
+            let target = Target {
+                node: found_on(client, node),
+                id: container.id.clone(),
+                name: container.name().to_string(),
+            };
+
+            (vec![target], errors)
+        }
+        None => {
+            errors.insert(0, error);
+            (vec![], errors)
+        }
+    }
+}
+
+/// Returns every running container as a target, only the containers on `node` when it is given,
+/// and the nodes that could not be searched as errors.
+async fn all_targets<'a, 'c>(
+    client: &'c Client,
+    node: Option<&'a str>,
+    options: &RunOptions<'_>,
+) -> (Vec<Target<'c>>, Vec<CommandError<'a>>) {
+    if let Some(address) = node {
+        if client.node(address).is_none() {
+            return (vec![], vec![CommandError::UnknownNode(address)]);
+        }
+    }
+
+    let (containers, errors) = list_containers(client, false, options).await;
+    let targets = containers
+        .into_iter()
+        .filter(|(hostname, _)| node.is_none_or(|node| node == hostname))
+        .map(|(hostname, container)| Target {
+            node: found_on(client, &hostname),
+            id: container.id.clone(),
+            name: container.name().to_string(),
+        })
+        .collect();
+
+    (
+        targets,
+        errors.into_iter().map(CommandError::NodeError).collect(),
+    )
+}
+
+/// Checks if `docker inspect` failed because the object does not exist on the node.
+fn is_missing(output: &NodeOutput) -> bool {
+    !output.success() && output.stderr.contains("No such object")
+}
+
+/// The exit code of docker-manager, derived from the results of all nodes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitCode {
+    /// The command succeeded on every node
+    Success = 0,
+    /// The command failed on at least one node, either remotely or because the node could not be
+    /// reached
+    Failure = 1,
+    /// None of the given containers were found, or there were not enough nodes to place a new
+    /// container on
+    NoMatch = 2,
+    /// A container that has to be unique was found more than once
+    Ambiguous = 3,
+    /// None of the nodes could be reached
+    Connection = 4,
+}
+
+impl ExitCode {
+    pub fn from_results(results: &[Result<NodeOutput, CommandError>]) -> Self {
+        let errors: Vec<&CommandError> = results.iter().filter_map(|r| r.as_ref().err()).collect();
+
+        if errors
+            .iter()
+            .any(|e| matches!(e, CommandError::MutlipleNodesFound(_)))
+        {
+            Self::Ambiguous
+        } else if errors.iter().any(|e| {
+            matches!(
+                e,
+                CommandError::NoNodesFound(_)
+                    | CommandError::NoMultipleNodesFound(_)
+                    | CommandError::UnknownNode(_)
+                    | CommandError::EmptyGroup(_)
+                    | CommandError::NotEnoughNodes(..)
+            )
+        }) {
+            Self::NoMatch
+        } else if !errors.is_empty() && errors.len() == results.len() {
+            Self::Connection
+        } else if !errors.is_empty() || results.iter().flatten().any(|output| !output.success()) {
+            Self::Failure
+        } else {
+            Self::Success
         }
     }
 }
 
 pub enum CommandError<'a> {
     NoNodesFound(&'a str),
+    /// The node passed to `--node` is not one of the selected nodes
+    UnknownNode(&'a str),
     NoMultipleNodesFound(Vec<&'a str>),
-    MutlipleNodesFound(Vec<String>),
+    /// `(node, container)` of every container that matched where only one may
+    MutlipleNodesFound(Vec<(String, ContainerSummary)>),
+    /// None of the selected nodes are in the group passed to `--on-group`
+    EmptyGroup(&'a str),
+    /// Fewer nodes than the wanted number of replicas could be reached, `(wanted, reachable)`
+    NotEnoughNodes(usize, usize),
     NodeError(NodeError),
 }
 
+impl<'a> CommandError<'a> {
+    /// Returns the hostname of the node the error occured on, if it is tied to a single node.
+    pub fn hostname(&self) -> Option<&str> {
+        match self {
+            Self::NodeError(node_error) => Some(node_error.hostname()),
+            _ => None,
+        }
+    }
+}
+
 impl<'a> From<NodeError> for CommandError<'a> {
     fn from(node_error: NodeError) -> Self {
         Self::NodeError(node_error)
@@ -425,10 +1021,39 @@ impl<'a> std::fmt::Display for CommandError<'a> {
                 "No nodes found containing the following containers:\n{}",
                 container_ids.join("\n")
             ),
-            Self::MutlipleNodesFound(nodes) => write!(
+            Self::UnknownNode(node) => write!(f, "No node named {} was selected", node),
+            Self::MutlipleNodesFound(containers) => {
+                let nodes: HashSet<&str> =
+                    containers.iter().map(|(node, _)| node.as_str()).collect();
+                // `--node` only tells containers apart that are on different nodes, the id
+                // tells apart the ones that share a node
+                let (hint, with_id) = match (nodes.len(), containers.len()) {
+                    (1, _) => ("by its full id", true),
+                    (nodes, containers) if nodes == containers => ("with --node", false),
+                    _ => ("with --node and its full id", true),
+                };
+                let containers: Vec<String> = containers
+                    .iter()
+                    .map(|(node, container)| match with_id {
+                        true => format!("{}: {} ({})", node, container.name(), container.id),
+                        false => format!("{}: {}", node, container.name()),
+                    })
+                    .collect();
+
+                write!(
+                    f,
+                    "Multiple containers found with matching criteria, pick one {}:\n{}",
+                    hint,
+                    containers.join("\n")
+                )
+            }
+            Self::EmptyGroup(group) => {
+                write!(f, "None of the selected nodes are in the group {}", group)
+            }
+            Self::NotEnoughNodes(wanted, reachable) => write!(
                 f,
-                "Multiple nodes found with matching criteria:\n{}",
-                nodes.join("\n")
+                "Only {} of the {} nodes needed to place the container on could be reached",
+                reachable, wanted
             ),
             Self::NodeError(node_error) => write!(f, "{}", node_error),
         }
@@ -437,25 +1062,158 @@ impl<'a> std::fmt::Display for CommandError<'a> {
 
 #[cfg(test)]
 mod test {
-    use super::CommandError;
+    use std::time::Duration;
+
+    use super::{load_order, CommandError, ContainerSummary, ExitCode, InfoSummary, NodeOutput};
+    use crate::client::NodeError;
+
+    fn output(exit_code: i32) -> Result<NodeOutput, CommandError<'static>> {
+        Ok(NodeOutput {
+            node: "abc".into(),
+            container: None,
+            exit_code: Some(exit_code),
+            stdout: String::new(),
+            stderr: String::new(),
+            duration: Duration::ZERO,
+        })
+    }
+
+    fn timeout() -> Result<NodeOutput, CommandError<'static>> {
+        Err(CommandError::NodeError(NodeError::Timeout(
+            "def".into(),
+            Duration::from_secs(1),
+        )))
+    }
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(ExitCode::Success, ExitCode::from_results(&[]));
+        assert_eq!(
+            ExitCode::Success,
+            ExitCode::from_results(&[output(0), output(0)])
+        );
+        assert_eq!(
+            ExitCode::Failure,
+            ExitCode::from_results(&[output(0), output(1)])
+        );
+        assert_eq!(
+            ExitCode::Failure,
+            ExitCode::from_results(&[output(0), timeout()])
+        );
+        assert_eq!(
+            ExitCode::Connection,
+            ExitCode::from_results(&[timeout(), timeout()])
+        );
+        assert_eq!(
+            ExitCode::NoMatch,
+            ExitCode::from_results(&[Err(CommandError::NoNodesFound("web"))])
+        );
+        assert_eq!(
+            ExitCode::NoMatch,
+            ExitCode::from_results(&[Err(CommandError::NotEnoughNodes(3, 2))])
+        );
+        assert_eq!(
+            ExitCode::NoMatch,
+            ExitCode::from_results(&[Err(CommandError::EmptyGroup("prod"))])
+        );
+        assert_eq!(
+            ExitCode::Ambiguous,
+            ExitCode::from_results(&[Err(CommandError::MutlipleNodesFound(vec![]))])
+        );
+    }
+
+    fn web(id: &str) -> ContainerSummary {
+        ContainerSummary {
+            id: id.into(),
+            names: "web".into(),
+            ..Default::default()
+        }
+    }
 
     #[test]
     fn test_command_error_multiple_found_diplay() {
-        let error = CommandError::MutlipleNodesFound(vec!["abc".into(), "def".into()]);
+        let error = CommandError::MutlipleNodesFound(vec![
+            ("abc".into(), web("3f8a00f137a0")),
+            ("def".into(), web("a1b2c3d4e5f6")),
+        ]);
 
         let correct_string: String =
-            "Multiple nodes found with matching criteria:\nabc\ndef".into();
+            "Multiple containers found with matching criteria, pick one with --node:\nabc: web\ndef: web"
+                .into();
+
+        assert_eq!(correct_string, format!("{}", error));
+    }
+
+    #[test]
+    fn test_command_error_multiple_found_on_one_node_diplay() {
+        let error = CommandError::MutlipleNodesFound(vec![
+            (
+                "abc".into(),
+                web("3f8a00f137a0b1c2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6"),
+            ),
+            ("abc".into(), web("a1b2c3d4e5f6")),
+        ]);
+
+        let correct_string: String =
+            "Multiple containers found with matching criteria, pick one by its full id:\nabc: web (3f8a00f137a0b1c2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6)\nabc: web (a1b2c3d4e5f6)"
+                .into();
+
+        assert_eq!(correct_string, format!("{}", error));
+    }
+
+    #[test]
+    fn test_command_error_multiple_found_on_some_nodes_diplay() {
+        let error = CommandError::MutlipleNodesFound(vec![
+            ("abc".into(), web("3f8a00f137a0")),
+            ("abc".into(), web("a1b2c3d4e5f6")),
+            ("def".into(), web("0f1e2d3c4b5a")),
+        ]);
+
+        let correct_string: String =
+            "Multiple containers found with matching criteria, pick one with --node and its full id:\nabc: web (3f8a00f137a0)\nabc: web (a1b2c3d4e5f6)\ndef: web (0f1e2d3c4b5a)"
+                .into();
 
         assert_eq!(correct_string, format!("{}", error));
     }
 
     #[test]
     fn test_command_error_no_node_found_diplay() {
-        let error = CommandError::NoNodesFound("some_container_id".into());
+        let error = CommandError::NoNodesFound("some_container_id");
 
         let correct_string: String =
             "No node found containing the following container: some_container_id".into();
 
         assert_eq!(correct_string, format!("{}", error));
     }
+
+    #[test]
+    fn test_load_order() {
+        let info = |containers_running, ncpu, mem_total| InfoSummary {
+            containers_running,
+            ncpu,
+            mem_total,
+        };
+        let mut loads = [
+            ("web4", info(1, 4, 8)),
+            ("web3", info(3, 8, 16)),
+            ("web2", info(1, 8, 8)),
+            ("web1", info(1, 4, 8)),
+            ("web5", info(1, 4, 16)),
+        ];
+
+        loads.sort_by(|(a, a_info), (b, b_info)| load_order((a, a_info), (b, b_info)));
+
+        assert_eq!(
+            vec!["web2", "web5", "web1", "web4", "web3"],
+            loads.iter().map(|(node, _)| *node).collect::<Vec<&str>>()
+        );
+    }
+
+    #[test]
+    fn test_command_error_empty_group_display() {
+        assert_eq!(
+            "None of the selected nodes are in the group prod",
+            CommandError::EmptyGroup("prod").to_string()
+        );
+    }
 }